@@ -1,50 +1,705 @@
-use crate::models::{Movie, Series, Anime, Stream, SearchResult};
+use crate::models::{Content, Movie, Series, Anime, Stream, StreamBehaviorHints, StreamSourceStatus, StreamsProgress, StreamsComplete, SearchResult, Season, MetaDetails, MetaItem, MetaVideo, Addon, AddonManifest, AddonCatalog, AddonCatalogExtra, AddonResource, CatalogExtra, HomeRow, HomeRowSetting, RankingProfile, RankingSettings};
+use crate::error::DeckflixError;
+use crate::http_cache::ResponseCache;
+use crate::release_parser;
+use crate::stream_ranking;
+use crate::torrent_cache::normalize_info_hash;
+use crate::torrent_streamer::parse_magnet;
+use futures_util::stream::{FuturesUnordered, StreamExt};
 use reqwest::Client;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
 use serde_json::Value;
-use std::time::Duration;
+use std::collections::{HashMap, HashSet};
+use std::future::Future;
+use std::path::{Path, PathBuf};
+use std::sync::RwLock;
+use std::time::{Duration, Instant};
+
+const ADDONS_FILE: &str = "addons.json";
+const HOME_ROWS_FILE: &str = "home_rows.json";
+const RANKING_FILE: &str = "ranking_profiles.json";
+const FALLBACK_TRACKERS_FILE: &str = "fallback_trackers.json";
+
+// Added to torrent streams that come without any tracker source, so peers can
+// be found before the DHT has any. Replaced by the user's list once one is saved;
+// an empty list leaves such torrents to the DHT.
+const DEFAULT_FALLBACK_TRACKERS: [&str; 2] = [
+    "udp://tracker.opentrackr.org:1337/announce",
+    "udp://open.demonii.com:1337/announce",
+];
+
+// Deadline for a single addon during a fan-out, so one dead source can't stall the UI
+const ADDON_TIMEOUT: Duration = Duration::from_secs(8);
+
+// How long cached responses stay fresh when the addon sends no cacheMaxAge
+const CATALOG_CACHE_TTL: Duration = Duration::from_secs(6 * 60 * 60);
+const META_CACHE_TTL: Duration = Duration::from_secs(24 * 60 * 60);
+const SEARCH_CACHE_TTL: Duration = Duration::from_secs(60 * 60);
+
+// With a stale copy on hand, give up on a slow network sooner than the addon deadline
+const STALE_FALLBACK_TIMEOUT: Duration = Duration::from_secs(4);
+
+// Items kept from each addon's catalog page (Steam Deck performance). Pages are
+// cut before merging, so the next page is requested with `skip` raised by this
+// much and nothing between two pages is dropped.
+const MOVIES_PAGE_LIMIT: usize = 50;
+const SERIES_PAGE_LIMIT: usize = 100;
 
 pub struct AddonClient {
     client: Client,
-    base_urls: Vec<String>,
+    // Settings sit behind their own locks, which are never held across a request,
+    // so commands run concurrently; requests work on a copy of the addon list
+    addons: RwLock<Vec<Addon>>,
+    addons_path: PathBuf,
+    home_rows: RwLock<Vec<HomeRowSetting>>,
+    home_rows_path: PathBuf,
+    ranking: RwLock<RankingSettings>,
+    ranking_path: PathBuf,
+    fallback_trackers: RwLock<Vec<String>>,
+    fallback_trackers_path: PathBuf,
+    cache: ResponseCache,
+}
+
+// Something a fan-out request is sent to, named in the logs
+trait FanOutTarget: Copy {
+    fn label(&self) -> String;
+}
+
+impl FanOutTarget for &Addon {
+    fn label(&self) -> String {
+        self.name.clone()
+    }
+}
+
+impl FanOutTarget for (&Addon, &AddonCatalog) {
+    fn label(&self) -> String {
+        format!("{} ({}/{})", self.0.name, self.1.catalog_type, self.1.id)
+    }
 }
 
 impl AddonClient {
-    pub fn new() -> Self {
+    pub fn new(data_dir: PathBuf, cache_dir: PathBuf) -> Self {
         let client = Client::builder()
             .timeout(Duration::from_secs(10))
             .build()
             .expect("Failed to create HTTP client");
 
-        // Use only the two most reliable streaming addon sources
-        // This reduces fake/mislabeled torrents and improves stream quality
-        let base_urls = vec![
-            "https://v3-cinemeta.strem.io".to_string(),                    // Metadata only (movies/series info)
-            "https://torrentio.strem.fun".to_string(),                     // Primary torrent source (most reliable)
-            "https://thepiratebay-plus.strem.fun".to_string(),             // TPB torrents (backup source)
+        let addons_path = data_dir.join(ADDONS_FILE);
+        let addons = Self::load_addons(&addons_path);
+
+        println!("[RUST] [ADDONS] Loaded {} installed addons from {}", addons.len(), addons_path.display());
+        for (index, addon) in addons.iter().enumerate() {
+            println!("[RUST] [ADDONS]   {}. {} ({})", index + 1, addon.name, addon.base_url);
+        }
+
+        let home_rows_path = data_dir.join(HOME_ROWS_FILE);
+        let home_rows = match Self::read_json_file(&home_rows_path) {
+            Ok(Some(home_rows)) => home_rows,
+            Ok(None) => Vec::new(),
+            Err(e) => {
+                println!("[RUST] [HOME_ROWS] ERROR: {}, using default row order", e);
+                Vec::new()
+            }
+        };
+
+        let ranking_path = data_dir.join(RANKING_FILE);
+        let ranking = match Self::read_json_file(&ranking_path) {
+            Ok(Some(ranking)) => ranking,
+            Ok(None) => stream_ranking::default_settings(),
+            Err(e) => {
+                println!("[RUST] [RANKING] ERROR: {}, using default ranking profiles", e);
+                stream_ranking::default_settings()
+            }
+        };
+
+        let fallback_trackers_path = data_dir.join(FALLBACK_TRACKERS_FILE);
+        let fallback_trackers = match Self::read_json_file(&fallback_trackers_path) {
+            Ok(Some(trackers)) => trackers,
+            Ok(None) => DEFAULT_FALLBACK_TRACKERS.iter().map(|t| t.to_string()).collect(),
+            Err(e) => {
+                println!("[RUST] [TRACKERS] ERROR: {}, using default fallback trackers", e);
+                DEFAULT_FALLBACK_TRACKERS.iter().map(|t| t.to_string()).collect()
+            }
+        };
+        println!("[RUST] [TRACKERS] Fallback trackers: {:?}", fallback_trackers);
+
+        let cache = ResponseCache::new(cache_dir);
+
+        Self {
+            client,
+            addons: RwLock::new(addons),
+            addons_path,
+            home_rows: RwLock::new(home_rows),
+            home_rows_path,
+            ranking: RwLock::new(ranking),
+            ranking_path,
+            fallback_trackers: RwLock::new(fallback_trackers),
+            fallback_trackers_path,
+            cache,
+        }
+    }
+
+    fn load_addons(path: &Path) -> Vec<Addon> {
+        match Self::read_json_file(path) {
+            Ok(Some(addons)) => addons,
+            Ok(None) => {
+                println!("[RUST] [ADDONS] No addon registry found, using default addons");
+                Self::default_addons()
+            }
+            Err(e) => {
+                println!("[RUST] [ADDONS] ERROR: {}, using default addons", e);
+                Self::default_addons()
+            }
+        }
+    }
+
+    fn save_addons(&self, addons: &[Addon]) -> Result<(), DeckflixError> {
+        Self::write_json_file(&self.addons_path, addons)?;
+        println!("[RUST] [ADDONS] Saved {} addons to {}", addons.len(), self.addons_path.display());
+        Ok(())
+    }
+
+    // Returns Ok(None) when the file doesn't exist yet
+    fn read_json_file<T: DeserializeOwned>(path: &Path) -> Result<Option<T>, DeckflixError> {
+        if !path.exists() {
+            return Ok(None);
+        }
+
+        let contents = std::fs::read_to_string(path)
+            .map_err(|e| DeckflixError::storage(format!("Failed to read {}: {}", path.display(), e)))?;
+
+        serde_json::from_str(&contents)
+            .map(Some)
+            .map_err(|e| DeckflixError::parse(format!("Failed to parse {}: {}", path.display(), e)))
+    }
+
+    fn write_json_file<T: Serialize + ?Sized>(path: &Path, value: &T) -> Result<(), DeckflixError> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)
+                .map_err(|e| DeckflixError::storage(format!("Failed to create {}: {}", parent.display(), e)))?;
+        }
+
+        let json = serde_json::to_string_pretty(value)
+            .map_err(|e| DeckflixError::storage(format!("Failed to serialize {}: {}", path.display(), e)))?;
+
+        std::fs::write(path, json)
+            .map_err(|e| DeckflixError::storage(format!("Failed to write {}: {}", path.display(), e)))
+    }
+
+    // Addons installed on first launch, before the user has changed anything.
+    // Manifests are built in so the app works without fetching them first.
+    fn default_addons() -> Vec<Addon> {
+        let strings = |values: &[&str]| values.iter().map(|v| v.to_string()).collect::<Vec<_>>();
+        let extra = |name: &str, options: Option<Vec<String>>| AddonCatalogExtra {
+            name: name.to_string(),
+            is_required: false,
+            options,
+        };
+        let catalog = |catalog_type: &str, id: &str, name: &str, genres: &[&str]| AddonCatalog {
+            catalog_type: catalog_type.to_string(),
+            id: id.to_string(),
+            name: name.to_string(),
+            extra: vec![
+                extra("search", None),
+                extra("skip", None),
+                extra("genre", Some(strings(genres))),
+            ],
+            extra_supported: Vec::new(),
+        };
+
+        let movie_genres = [
+            "Action", "Adventure", "Animation", "Biography", "Comedy", "Crime", "Documentary",
+            "Drama", "Family", "Fantasy", "History", "Horror", "Mystery", "Romance", "Sci-Fi",
+            "Sport", "Thriller", "War", "Western",
+        ];
+        let series_genres = [
+            "Action", "Adventure", "Animation", "Biography", "Comedy", "Crime", "Documentary",
+            "Drama", "Family", "Fantasy", "History", "Horror", "Mystery", "Romance", "Sci-Fi",
+            "Sport", "Thriller", "War", "Western", "Reality-TV", "Talk-Show", "Game-Show",
         ];
 
-        Self { client, base_urls }
+        vec![
+            // Metadata only (movies/series info)
+            Addon {
+                name: "Cinemeta".to_string(),
+                base_url: "https://v3-cinemeta.strem.io".to_string(),
+                manifest: AddonManifest {
+                    id: "com.linvo.cinemeta".to_string(),
+                    version: "3.0.0".to_string(),
+                    name: "Cinemeta".to_string(),
+                    description: "The official addon for movie and series catalogs".to_string(),
+                    resources: vec![
+                        AddonResource::Name("catalog".to_string()),
+                        AddonResource::Name("meta".to_string()),
+                    ],
+                    types: strings(&["movie", "series"]),
+                    catalogs: vec![
+                        catalog("movie", "top", "Popular", &movie_genres),
+                        catalog("series", "top", "Popular", &series_genres),
+                    ],
+                    id_prefixes: Some(strings(&["tt"])),
+                },
+            },
+            // Primary torrent source (most reliable)
+            Addon {
+                name: "Torrentio".to_string(),
+                base_url: "https://torrentio.strem.fun".to_string(),
+                manifest: AddonManifest {
+                    id: "com.stremio.torrentio.addon".to_string(),
+                    version: "0.0.14".to_string(),
+                    name: "Torrentio".to_string(),
+                    description: "Provides torrent streams from scraped torrent providers".to_string(),
+                    resources: vec![AddonResource::Name("stream".to_string())],
+                    types: strings(&["movie", "series", "anime"]),
+                    catalogs: Vec::new(),
+                    id_prefixes: Some(strings(&["tt", "kitsu"])),
+                },
+            },
+            // TPB torrents (backup source)
+            Addon {
+                name: "ThePirateBay+".to_string(),
+                base_url: "https://thepiratebay-plus.strem.fun".to_string(),
+                manifest: AddonManifest {
+                    id: "org.stremio.thepiratebay-plus".to_string(),
+                    version: "1.4.0".to_string(),
+                    name: "ThePirateBay+".to_string(),
+                    description: "Search for movies and series from ThePirateBay".to_string(),
+                    resources: vec![AddonResource::Name("stream".to_string())],
+                    types: strings(&["movie", "series"]),
+                    catalogs: Vec::new(),
+                    id_prefixes: Some(strings(&["tt"])),
+                },
+            },
+        ]
     }
 
-    pub async fn fetch_popular_movies(&self) -> Result<Vec<Movie>, String> {
-        println!("[RUST] [MOVIES_FETCH] Starting to fetch popular movies from real streaming sources...");
-        let mut all_movies = Vec::new();
-        let mut last_error = String::new();
+    // Installed addons that can answer a meta, stream or subtitles request, in the user's order
+    fn addons_for(&self, resource: &str, content_type: &str, id: Option<&str>) -> Vec<Addon> {
+        let addons: Vec<Addon> = self
+            .list_addons()
+            .into_iter()
+            .filter(|a| a.supports(resource, content_type, id))
+            .collect();
+
+        println!("[RUST] [ADDONS] {} addon(s) provide {}/{} for {}: {:?}",
+                addons.len(), resource, content_type, id.unwrap_or("-"),
+                addons.iter().map(|a| &a.name).collect::<Vec<_>>());
+        addons
+    }
+
+    // Installed addons whose catalog can be requested with these extras, in the user's order
+    fn catalog_addons(&self, content_type: &str, catalog_id: &str, extra: &CatalogExtra) -> Vec<Addon> {
+        let addons: Vec<Addon> = self
+            .list_addons()
+            .into_iter()
+            .filter(|a| a.catalog(content_type, catalog_id).is_some_and(|c| c.accepts(extra)))
+            .collect();
+
+        println!("[RUST] [ADDONS] {} addon(s) provide catalog/{}/{} with {:?}: {:?}",
+                addons.len(), content_type, catalog_id, extra,
+                addons.iter().map(|a| &a.name).collect::<Vec<_>>());
+        addons
+    }
+
+    // Stremio puts catalog extras in the last path segment,
+    // e.g. "/catalog/movie/top/genre=Sci-Fi&skip=100.json"
+    fn catalog_url(base_url: &str, content_type: &str, catalog_id: &str, extra: &CatalogExtra) -> String {
+        if extra.is_empty() {
+            return format!("{}/catalog/{}/{}.json", base_url, content_type, catalog_id);
+        }
+
+        let extra_path = extra
+            .iter()
+            .map(|(name, value)| format!("{}={}", urlencoding::encode(name), urlencoding::encode(value)))
+            .collect::<Vec<_>>()
+            .join("&");
+
+        format!("{}/catalog/{}/{}/{}.json", base_url, content_type, catalog_id, extra_path)
+    }
+
+    // Genre options the installed addons declare for a catalog, for genre rows
+    pub fn catalog_genres(&self, content_type: &str, catalog_id: &str) -> Vec<String> {
+        let mut genres: Vec<String> = Vec::new();
+
+        for addon in self.addons.read().unwrap().iter() {
+            let options = addon
+                .catalog(content_type, catalog_id)
+                .and_then(|c| c.extra.iter().find(|e| e.name == "genre"))
+                .and_then(|e| e.options.as_ref());
+
+            for genre in options.into_iter().flatten() {
+                if !genres.contains(genre) {
+                    genres.push(genre.clone());
+                }
+            }
+        }
+
+        genres
+    }
+
+    // Removes repeated ids while keeping the catalog order (popularity, for "top")
+    fn dedup_by_id<T: Content>(items: &mut Vec<T>) {
+        let mut seen = HashSet::new();
+        items.retain(|item| seen.insert(item.id().to_string()));
+    }
+
+    pub fn clear_cache(&self) -> Result<(), DeckflixError> {
+        println!("[RUST] [CACHE] Clearing cached addon responses");
+        self.cache.clear()
+    }
+
+    pub fn list_addons(&self) -> Vec<Addon> {
+        self.addons.read().unwrap().clone()
+    }
+
+    pub async fn install_addon(&self, manifest_url: &str) -> Result<Addon, DeckflixError> {
+        let base_url = Self::normalize_addon_url(manifest_url)?;
+        let url = format!("{}/manifest.json", base_url);
+        println!("[RUST] [ADDONS] Installing addon from manifest: {}", url);
+
+        let response = self
+            .client
+            .get(&url)
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            return Err(DeckflixError::http_status(response.status(), &url));
+        }
+
+        let manifest: AddonManifest = response
+            .json()
+            .await
+            .map_err(|e| DeckflixError::parse(format!("Invalid addon manifest: {}", e)))?;
+
+        println!("[RUST] [ADDONS] Parsed manifest: {} v{} (id: {}, resources: {:?}, types: {:?}, catalogs: {})",
+                manifest.name, manifest.version, manifest.id, manifest.resources, manifest.types, manifest.catalogs.len());
+
+        let addon = Addon {
+            name: manifest.name.clone(),
+            base_url,
+            manifest,
+        };
+
+        // Reinstalling an addon updates it in place so it keeps its position
+        let mut addons = self.addons.write().unwrap();
+        if let Some(existing) = addons.iter_mut().find(|a| a.manifest.id == addon.manifest.id) {
+            println!("[RUST] [ADDONS] Addon {} already installed, updating it", addon.manifest.id);
+            *existing = addon.clone();
+        } else {
+            addons.push(addon.clone());
+        }
+
+        self.save_addons(&addons)?;
+        Ok(addon)
+    }
+
+    pub fn remove_addon(&self, addon_id: &str) -> Result<(), DeckflixError> {
+        let mut addons = self.addons.write().unwrap();
+        let original_count = addons.len();
+        addons.retain(|a| a.manifest.id != addon_id);
+
+        if addons.len() == original_count {
+            return Err(DeckflixError::not_found(format!("Addon not installed: {}", addon_id)));
+        }
+
+        println!("[RUST] [ADDONS] Removed addon: {}", addon_id);
+        self.save_addons(&addons)
+    }
+
+    pub fn reorder_addons(&self, addon_ids: &[String]) -> Result<Vec<Addon>, DeckflixError> {
+        let mut addons = self.addons.write().unwrap();
+        let mut remaining = std::mem::take(&mut *addons);
+        let mut reordered = Vec::with_capacity(remaining.len());
+
+        for addon_id in addon_ids {
+            if let Some(pos) = remaining.iter().position(|a| &a.manifest.id == addon_id) {
+                reordered.push(remaining.remove(pos));
+            }
+        }
+
+        // Addons missing from the requested order keep their relative position at the end
+        reordered.append(&mut remaining);
+        *addons = reordered;
+
+        println!("[RUST] [ADDONS] New addon order: {:?}", addons.iter().map(|a| &a.name).collect::<Vec<_>>());
+        self.save_addons(&addons)?;
+        Ok(addons.clone())
+    }
+
+    // Accepts "https://host/manifest.json", "https://host/" or "stremio://host/manifest.json"
+    // and returns the addon base URL without a trailing slash
+    fn normalize_addon_url(manifest_url: &str) -> Result<String, DeckflixError> {
+        let trimmed = manifest_url.trim();
+
+        let url = if let Some(rest) = trimmed.strip_prefix("stremio://") {
+            format!("https://{}", rest)
+        } else {
+            trimmed.to_string()
+        };
+
+        if !url.starts_with("http://") && !url.starts_with("https://") {
+            return Err(DeckflixError::invalid_request(format!("Invalid addon URL: {}", manifest_url)));
+        }
+
+        let base_url = url
+            .trim_end_matches('/')
+            .trim_end_matches("/manifest.json")
+            .trim_end_matches('/')
+            .to_string();
+
+        Ok(base_url)
+    }
+
+    // Queries every addon concurrently, each with its own deadline. Results are passed
+    // to `on_result` as they arrive; returning false from it stops waiting for the rest.
+    async fn fan_out<J, T, F, Fut>(
+        &self,
+        targets: impl IntoIterator<Item = J>,
+        request: F,
+        mut on_result: impl FnMut(J, Result<T, DeckflixError>) -> bool,
+    ) where
+        J: FanOutTarget,
+        F: Fn(J) -> Fut,
+        Fut: Future<Output = Result<T, DeckflixError>>,
+    {
+        let start_time = Instant::now();
+        let mut pending: FuturesUnordered<_> = targets
+            .into_iter()
+            .map(|target| {
+                let request = request(target);
+                async move {
+                    let result = match tokio::time::timeout(ADDON_TIMEOUT, request).await {
+                        Ok(result) => result,
+                        Err(_) => Err(DeckflixError::timeout(ADDON_TIMEOUT)),
+                    };
+                    (target, result)
+                }
+            })
+            .collect();
+
+        while let Some((target, result)) = pending.next().await {
+            println!("[RUST] [FAN_OUT] {} answered after {:?} ({})",
+                    target.label(), start_time.elapsed(), if result.is_ok() { "ok" } else { "error" });
+
+            if !on_result(target, result) {
+                // Dropping the remaining futures cancels their requests
+                println!("[RUST] [FAN_OUT] Stopping early, {} request(s) still pending", pending.len());
+                break;
+            }
+        }
+    }
+
+    // Every catalog of every installed addon as a home row, in the saved order.
+    // Rows that aren't in the saved settings yet (new addons) go last, visible.
+    pub fn list_home_rows(&self) -> Vec<HomeRow> {
+        let mut rows: Vec<HomeRow> = Vec::new();
+
+        for addon in self.addons.read().unwrap().iter() {
+            for catalog in &addon.manifest.catalogs {
+                // Search-only catalogs can't be listed without a query
+                if !catalog.accepts(&CatalogExtra::new()) {
+                    continue;
+                }
+
+                let catalog_name = if catalog.name.is_empty() { &catalog.id } else { &catalog.name };
+                let mut type_name = catalog.catalog_type.clone();
+                if let Some(first) = type_name.get_mut(0..1) {
+                    first.make_ascii_uppercase();
+                }
+
+                rows.push(HomeRow {
+                    id: format!("{}/{}/{}", addon.manifest.id, catalog.catalog_type, catalog.id),
+                    name: format!("{} - {}", catalog_name, type_name),
+                    addon_id: addon.manifest.id.clone(),
+                    addon_name: addon.name.clone(),
+                    content_type: catalog.catalog_type.clone(),
+                    catalog_id: catalog.id.clone(),
+                    hidden: false,
+                    items: Vec::new(),
+                });
+            }
+        }
+
+        let home_rows = self.home_rows.read().unwrap();
+        let position = |row: &HomeRow| {
+            home_rows
+                .iter()
+                .position(|setting| setting.id == row.id)
+                .unwrap_or(usize::MAX)
+        };
 
-        println!("[RUST] [MOVIES_FETCH] Available streaming addon URLs: {:?}", self.base_urls);
-        println!("[RUST] [MOVIES_FETCH] Primary metadata source: https://v3-cinemeta.strem.io/catalog/movie/top.json");
-        println!("[RUST] [MOVIES_FETCH] Torrent sources: Torrentio (primary), ThePirateBay+ (backup)");
+        for row in &mut rows {
+            row.hidden = home_rows.iter().any(|setting| setting.id == row.id && setting.hidden);
+        }
+        rows.sort_by_key(|row| position(row)); // stable, so unsaved rows keep addon order
+
+        rows
+    }
+
+    pub fn set_home_rows(&self, settings: Vec<HomeRowSetting>) -> Result<Vec<HomeRow>, DeckflixError> {
+        println!("[RUST] [HOME_ROWS] Saving {} row settings ({} hidden)",
+                settings.len(), settings.iter().filter(|s| s.hidden).count());
+
+        {
+            let mut home_rows = self.home_rows.write().unwrap();
+            *home_rows = settings;
+            Self::write_json_file(&self.home_rows_path, &*home_rows)?;
+        }
+        Ok(self.list_home_rows())
+    }
+
+    pub fn list_ranking_profiles(&self) -> RankingSettings {
+        self.ranking.read().unwrap().clone()
+    }
+
+    // The profile streams are currently ranked with (falls back to the first one)
+    fn active_ranking_profile(&self) -> RankingProfile {
+        let ranking = self.ranking.read().unwrap();
+        ranking
+            .profiles
+            .iter()
+            .find(|p| p.id == ranking.active)
+            .or_else(|| ranking.profiles.first())
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    // Adds the profile, or replaces the saved one with the same id
+    pub fn save_ranking_profile(&self, profile: RankingProfile) -> Result<RankingSettings, DeckflixError> {
+        if profile.id.trim().is_empty() {
+            return Err(DeckflixError::invalid_request("Ranking profile id must not be empty"));
+        }
+
+        println!("[RUST] [RANKING] Saving profile {} ({})", profile.id, profile.name);
+        let mut ranking = self.ranking.write().unwrap();
+        match ranking.profiles.iter_mut().find(|p| p.id == profile.id) {
+            Some(existing) => *existing = profile,
+            None => ranking.profiles.push(profile),
+        }
+
+        self.save_ranking(&ranking)
+    }
+
+    pub fn remove_ranking_profile(&self, profile_id: &str) -> Result<RankingSettings, DeckflixError> {
+        let mut ranking = self.ranking.write().unwrap();
+        if ranking.profiles.len() == 1 && ranking.profiles[0].id == profile_id {
+            return Err(DeckflixError::invalid_request("Cannot remove the last ranking profile"));
+        }
+
+        let before = ranking.profiles.len();
+        ranking.profiles.retain(|p| p.id != profile_id);
+        if ranking.profiles.len() == before {
+            return Err(DeckflixError::not_found(format!("Ranking profile not found: {}", profile_id)));
+        }
+
+        if ranking.active == profile_id {
+            ranking.active = ranking.profiles[0].id.clone();
+        }
+
+        println!("[RUST] [RANKING] Removed profile {}, active is now {}", profile_id, ranking.active);
+        self.save_ranking(&ranking)
+    }
+
+    pub fn set_active_ranking_profile(&self, profile_id: &str) -> Result<RankingSettings, DeckflixError> {
+        let mut ranking = self.ranking.write().unwrap();
+        if !ranking.profiles.iter().any(|p| p.id == profile_id) {
+            return Err(DeckflixError::not_found(format!("Ranking profile not found: {}", profile_id)));
+        }
+
+        println!("[RUST] [RANKING] Active profile: {}", profile_id);
+        ranking.active = profile_id.to_string();
+        self.save_ranking(&ranking)
+    }
+
+    fn save_ranking(&self, ranking: &RankingSettings) -> Result<RankingSettings, DeckflixError> {
+        Self::write_json_file(&self.ranking_path, ranking)?;
+        Ok(ranking.clone())
+    }
+
+    pub fn fallback_trackers(&self) -> Vec<String> {
+        self.fallback_trackers.read().unwrap().clone()
+    }
+
+    // Applies to streams fetched from now on
+    pub fn set_fallback_trackers(&self, trackers: Vec<String>) -> Result<Vec<String>, DeckflixError> {
+        for tracker in &trackers {
+            let scheme = reqwest::Url::parse(tracker).map(|url| url.scheme().to_string()).unwrap_or_default();
+            if !["udp", "http", "https", "ws", "wss"].contains(&scheme.as_str()) {
+                return Err(DeckflixError::invalid_request(format!("Not a tracker URL: {}", tracker)));
+            }
+        }
+
+        println!("[RUST] [TRACKERS] Saving {} fallback trackers", trackers.len());
+        let mut fallback_trackers = self.fallback_trackers.write().unwrap();
+        *fallback_trackers = trackers;
+        Self::write_json_file(&self.fallback_trackers_path, &*fallback_trackers)?;
+        Ok(fallback_trackers.clone())
+    }
+
+    // Fetches every visible home row concurrently; rows that fail or come back empty are left out
+    pub async fn fetch_home_rows(&self) -> Result<Vec<HomeRow>, DeckflixError> {
+        let mut rows: Vec<HomeRow> = self.list_home_rows().into_iter().filter(|row| !row.hidden).collect();
+        println!("[RUST] [HOME_ROWS] Fetching {} visible rows", rows.len());
+
+        let addons = self.list_addons();
+        let targets: Vec<(&Addon, &AddonCatalog)> = rows
+            .iter()
+            .filter_map(|row| {
+                let addon = addons.iter().find(|a| a.manifest.id == row.addon_id)?;
+                let catalog = addon.catalog(&row.content_type, &row.catalog_id)?;
+                Some((addon, catalog))
+            })
+            .collect();
+
+        let no_extra = CatalogExtra::new();
+        self.fan_out(
+            targets,
+            |(addon, catalog)| self.fetch_catalog_items(&addon.base_url, &catalog.catalog_type, &catalog.id, &no_extra),
+            |(addon, catalog), result| {
+                match result {
+                    Ok(items) => {
+                        let row_id = format!("{}/{}/{}", addon.manifest.id, catalog.catalog_type, catalog.id);
+                        if let Some(row) = rows.iter_mut().find(|row| row.id == row_id) {
+                            println!("[RUST] [HOME_ROWS] {} has {} items", row.name, items.len());
+                            row.items = items;
+                        }
+                    }
+                    Err(e) => println!("[RUST] [HOME_ROWS] Failed to fetch {}/{} from {}: {}",
+                                      catalog.catalog_type, catalog.id, addon.name, e),
+                }
+                true
+            },
+        ).await;
+
+        rows.retain(|row| !row.items.is_empty());
+        if rows.is_empty() {
+            return Err(DeckflixError::not_found("No home rows could be loaded from any addon"));
+        }
+
+        Ok(rows)
+    }
+
+    // `extra` pages and filters the catalog, e.g. {"skip": "50"} or {"genre": "Sci-Fi"};
+    // pages are MOVIES_PAGE_LIMIT apart
+    pub async fn fetch_popular_movies(&self, extra: &CatalogExtra) -> Result<Vec<Movie>, DeckflixError> {
+        println!("[RUST] [MOVIES_FETCH] Starting to fetch popular movies from real streaming sources (extra: {:?})...", extra);
+        let mut all_movies = Vec::new();
+        let mut last_error = None;
 
-        // Try Cinemeta first (prioritized)
-        for (index, base_url) in self.base_urls.iter().enumerate() {
-            println!("[RUST] [MOVIES_FETCH] Attempting to fetch from addon {} ({})", index + 1, base_url);
+        // Query every catalog addon at once; the first one in the user's order is prioritized
+        let addons = self.catalog_addons("movie", "top", extra);
+        if addons.is_empty() {
+            return Err(DeckflixError::not_found(format!("No installed addon provides a movie catalog with {:?}", extra)));
+        }
+        let primary_id = addons[0].manifest.id.clone();
 
-            let start_time = std::time::Instant::now();
-            match self.fetch_movies_from_addon(base_url, "top").await {
+        self.fan_out(&addons, |addon| self.fetch_movies_from_addon(&addon.base_url, "top", extra), |addon, result| {
+            match result {
                 Ok(mut movies) => {
-                    let duration = start_time.elapsed();
-                    println!("[RUST] [MOVIES_FETCH] Successfully fetched {} movies from {} in {:?}",
-                            movies.len(), base_url, duration);
+                    println!("[RUST] [MOVIES_FETCH] Successfully fetched {} movies from {}", movies.len(), addon.base_url);
+                    movies.truncate(MOVIES_PAGE_LIMIT);
 
                     // Log sample movie data for debugging
                     if !movies.is_empty() {
@@ -54,110 +709,111 @@ impl AddonClient {
                         }
                     }
 
-                    all_movies.append(&mut movies);
-
-                    // If we get movies from Cinemeta (first URL), that's sufficient
-                    if index == 0 && !all_movies.is_empty() {
-                        println!("[RUST] [MOVIES_FETCH] Got movies from primary source (Cinemeta), stopping here");
-                        break;
+                    // If we get movies from the primary addon, that's sufficient
+                    if addon.manifest.id == primary_id && !movies.is_empty() {
+                        println!("[RUST] [MOVIES_FETCH] Got movies from primary source, stopping here");
+                        all_movies = movies;
+                        return false;
                     }
+
+                    all_movies.append(&mut movies);
                 }
                 Err(e) => {
-                    let duration = start_time.elapsed();
-                    last_error = format!("Failed to fetch from {}: {}", base_url, e);
-                    println!("[RUST] [MOVIES_FETCH] ERROR: {} (after {:?})", last_error, duration);
-                    continue;
+                    println!("[RUST] [MOVIES_FETCH] ERROR: Failed to fetch from {}: {}", addon.base_url, e);
+                    last_error = Some(e);
                 }
             }
-        }
+            true
+        }).await;
 
         if all_movies.is_empty() {
-            let error_msg = format!("No movies found from any addon. Last error: {}", last_error);
-            println!("[RUST] [MOVIES_FETCH] CRITICAL ERROR: {}", error_msg);
-            return Err(error_msg);
+            // Surface the underlying failure so the UI can tell offline from empty
+            let error = last_error.unwrap_or_else(|| DeckflixError::not_found("No movies found from any addon"));
+            println!("[RUST] [MOVIES_FETCH] CRITICAL ERROR: {}", error);
+            return Err(error);
         }
 
         println!("[RUST] [MOVIES_FETCH] Processing {} total movies...", all_movies.len());
 
-        // Remove duplicates and limit results for better performance
+        // Remove duplicates; each page was already limited to MOVIES_PAGE_LIMIT
         let original_count = all_movies.len();
-        all_movies.sort_by(|a, b| a.id.cmp(&b.id));
-        all_movies.dedup_by(|a, b| a.id == b.id);
-
-        let after_dedup = all_movies.len();
-        all_movies.truncate(50); // Limit to 50 for Steam Deck performance
-        let final_count = all_movies.len();
+        Self::dedup_by_id(&mut all_movies);
 
         println!("[RUST] [MOVIES_FETCH] Movie processing complete:");
         println!("[RUST] [MOVIES_FETCH]   Original count: {}", original_count);
-        println!("[RUST] [MOVIES_FETCH]   After deduplication: {}", after_dedup);
-        println!("[RUST] [MOVIES_FETCH]   Final count (after truncation): {}", final_count);
+        println!("[RUST] [MOVIES_FETCH]   After deduplication: {}", all_movies.len());
 
         Ok(all_movies)
     }
 
-    pub async fn fetch_popular_series(&self) -> Result<Vec<Series>, String> {
-        println!("[RUST] [SERIES_FETCH] Starting to fetch popular series using correct Stremio v3 structure...");
+    // `extra` pages and filters the catalog, e.g. {"skip": "100"} or {"genre": "Comedy"};
+    // pages are SERIES_PAGE_LIMIT apart
+    pub async fn fetch_popular_series(&self, extra: &CatalogExtra) -> Result<Vec<Series>, DeckflixError> {
+        println!("[RUST] [SERIES_FETCH] Starting to fetch popular series using correct Stremio v3 structure (extra: {:?})...", extra);
         let mut all_series = Vec::new();
 
-        println!("[RUST] [SERIES_FETCH] Correct endpoint: https://v3-cinemeta.strem.io/catalog/series/top.json");
-
-        for base_url in &self.base_urls {
-            match self.fetch_series_from_addon(base_url, "top").await {
+        let addons = self.catalog_addons("series", "top", extra);
+        self.fan_out(&addons, |addon| self.fetch_series_from_addon(&addon.base_url, "top", extra), |addon, result| {
+            match result {
                 Ok(mut series) => {
+                    series.truncate(SERIES_PAGE_LIMIT);
                     all_series.append(&mut series);
                 }
-                Err(e) => {
-                    eprintln!("Failed to fetch series from {}: {}", base_url, e);
-                    continue;
-                }
+                Err(e) => eprintln!("Failed to fetch series from {}: {}", addon.base_url, e),
             }
-        }
+            true
+        }).await;
 
         if all_series.is_empty() {
-            return Err("No series found from any addon".to_string());
+            return Err(DeckflixError::not_found("No series found from any addon"));
         }
 
-        // Remove duplicates and limit results
-        all_series.sort_by(|a, b| a.id.cmp(&b.id));
-        all_series.dedup_by(|a, b| a.id == b.id);
-        all_series.truncate(100);
+        // Remove duplicates; each page was already limited to SERIES_PAGE_LIMIT
+        Self::dedup_by_id(&mut all_series);
 
         Ok(all_series)
     }
 
-    pub async fn fetch_popular_anime(&self) -> Result<Vec<Anime>, String> {
+    pub async fn fetch_popular_anime(&self) -> Result<Vec<Anime>, DeckflixError> {
         println!("[RUST] [ANIME_FETCH] Starting to fetch anime from both movies and series endpoints...");
-        let mut all_anime = Vec::new();
+        let mut anime_series = Vec::new();
+        let mut anime_movies = Vec::new();
+
+        let no_extra = CatalogExtra::new();
+        let series_addons = self.catalog_addons("series", "top", &no_extra);
+        let movie_addons = self.catalog_addons("movie", "top", &no_extra);
 
-        for base_url in &self.base_urls {
-            // Fetch from series catalog
-            println!("[RUST] [ANIME_FETCH] Fetching anime from series catalog: {}", base_url);
-            match self.fetch_anime_from_addon(base_url, "top").await {
+        // Series and movie catalogs are fetched at the same time
+        let fetch_series = self.fan_out(&series_addons, |addon| self.fetch_anime_from_addon(&addon.base_url, "top"), |addon, result| {
+            match result {
                 Ok(mut anime) => {
-                    println!("[RUST] [ANIME_FETCH] Found {} anime series from {}", anime.len(), base_url);
-                    all_anime.append(&mut anime);
-                }
-                Err(e) => {
-                    eprintln!("Failed to fetch anime series from {}: {}", base_url, e);
+                    println!("[RUST] [ANIME_FETCH] Found {} anime series from {}", anime.len(), addon.base_url);
+                    anime_series.append(&mut anime);
                 }
+                Err(e) => eprintln!("Failed to fetch anime series from {}: {}", addon.base_url, e),
             }
+            true
+        });
 
-            // Also fetch from movie catalog and filter for anime
-            println!("[RUST] [ANIME_FETCH] Fetching anime from movie catalog: {}", base_url);
-            match self.fetch_anime_movies_from_addon(base_url, "top").await {
-                Ok(mut anime_movies) => {
-                    println!("[RUST] [ANIME_FETCH] Found {} anime movies from {}", anime_movies.len(), base_url);
-                    all_anime.append(&mut anime_movies);
-                }
-                Err(e) => {
-                    eprintln!("Failed to fetch anime movies from {}: {}", base_url, e);
+        // Also fetch from movie catalog and filter for anime
+        let fetch_movies = self.fan_out(&movie_addons, |addon| self.fetch_anime_movies_from_addon(&addon.base_url, "top"), |addon, result| {
+            match result {
+                Ok(mut anime) => {
+                    println!("[RUST] [ANIME_FETCH] Found {} anime movies from {}", anime.len(), addon.base_url);
+                    anime_movies.append(&mut anime);
                 }
+                Err(e) => eprintln!("Failed to fetch anime movies from {}: {}", addon.base_url, e),
             }
-        }
+            true
+        });
+
+        tokio::join!(fetch_series, fetch_movies);
+
+        let mut all_anime = anime_series;
+        all_anime.append(&mut anime_movies);
 
         if all_anime.is_empty() {
-            return Err("No anime found from any addon".to_string());
+            return Err(DeckflixError::not_found("No anime found from any addon"));
         }
 
         // Remove duplicates and limit results
@@ -170,7 +826,7 @@ impl AddonClient {
         Ok(all_anime)
     }
 
-    pub async fn search_content(&self, query: &str) -> Result<Vec<SearchResult>, String> {
+    pub async fn search_content(&self, query: &str) -> Result<Vec<SearchResult>, DeckflixError> {
         println!("[RUST] [SEARCH] Starting comprehensive search for query: '{}'", query);
 
         if query.len() < 2 {
@@ -178,34 +834,40 @@ impl AddonClient {
             return Ok(Vec::new());
         }
 
-        let mut all_results = Vec::new();
+        let mut movie_results = Vec::new();
+        let mut series_results = Vec::new();
 
-        // Search movies, series, and anime from all addons
-        for base_url in &self.base_urls {
-            println!("[RUST] [SEARCH] Searching in addon: {}", base_url);
+        // Search movies, series, and anime from every addon with a searchable catalog, all at once
+        let search_extra = CatalogExtra::from([("search".to_string(), query.to_string())]);
+        let movie_addons = self.catalog_addons("movie", "top", &search_extra);
+        let series_addons = self.catalog_addons("series", "top", &search_extra);
 
-            // Search movies
-            match self.search_movies_from_addon(base_url, query).await {
-                Ok(mut movie_results) => {
-                    println!("[RUST] [SEARCH] Found {} movie results from {}", movie_results.len(), base_url);
-                    all_results.append(&mut movie_results);
-                }
-                Err(e) => {
-                    println!("[RUST] [SEARCH] Failed to search movies from {}: {}", base_url, e);
+        let search_movies = self.fan_out(&movie_addons, |addon| self.search_movies_from_addon(&addon.base_url, query), |addon, result| {
+            match result {
+                Ok(mut results) => {
+                    println!("[RUST] [SEARCH] Found {} movie results from {}", results.len(), addon.base_url);
+                    movie_results.append(&mut results);
                 }
+                Err(e) => println!("[RUST] [SEARCH] Failed to search movies from {}: {}", addon.base_url, e),
             }
+            true
+        });
 
-            // Search series
-            match self.search_series_from_addon(base_url, query).await {
-                Ok(mut series_results) => {
-                    println!("[RUST] [SEARCH] Found {} series results from {}", series_results.len(), base_url);
-                    all_results.append(&mut series_results);
-                }
-                Err(e) => {
-                    println!("[RUST] [SEARCH] Failed to search series from {}: {}", base_url, e);
+        let search_series = self.fan_out(&series_addons, |addon| self.search_series_from_addon(&addon.base_url, query), |addon, result| {
+            match result {
+                Ok(mut results) => {
+                    println!("[RUST] [SEARCH] Found {} series results from {}", results.len(), addon.base_url);
+                    series_results.append(&mut results);
                 }
+                Err(e) => println!("[RUST] [SEARCH] Failed to search series from {}: {}", addon.base_url, e),
             }
-        }
+            true
+        });
+
+        tokio::join!(search_movies, search_series);
+
+        let mut all_results = movie_results;
+        all_results.append(&mut series_results);
 
         // Apply anime detection logic
         for result in &mut all_results {
@@ -227,102 +889,324 @@ impl AddonClient {
         Ok(all_results)
     }
 
-    pub async fn fetch_streams(&self, imdb_id: &str) -> Result<Vec<Stream>, String> {
-        println!("[RUST] [STREAMS_FETCH] Starting to fetch streams for IMDB ID: {} from {} sources", imdb_id, self.base_urls.len());
-        let mut all_streams = Vec::new();
-        let mut successful_sources = 0;
-        let mut failed_sources = Vec::new();
-
-        for base_url in &self.base_urls {
-            // Skip metadata-only sources for stream fetching
-            if base_url.contains("v3-cinemeta.strem.io") {
-                println!("[RUST] [STREAMS_FETCH] Skipping metadata-only source: {}", base_url);
-                continue;
-            }
+    pub async fn fetch_streams(&self, imdb_id: &str, min_streams: Option<usize>) -> Result<Vec<Stream>, DeckflixError> {
+        self.fetch_streams_for("movie", imdb_id, min_streams).await
+    }
 
-            let stream_url = format!("{}/stream/movie/{}.json", base_url, imdb_id);
-            println!("[RUST] [STREAMS_FETCH] Trying torrent source: {}", stream_url);
+    // Episodes use the Stremio "series" type with a "{imdb_id}:{season}:{episode}" video id
+    pub async fn fetch_episode_streams(
+        &self,
+        imdb_id: &str,
+        season: u32,
+        episode: u32,
+        min_streams: Option<usize>,
+    ) -> Result<Vec<Stream>, DeckflixError> {
+        let video_id = format!("{}:{}:{}", imdb_id, season, episode);
+        println!("[RUST] [STREAMS_FETCH] Fetching episode streams for {} S{}E{} (video ID: {})", imdb_id, season, episode, video_id);
+        self.fetch_streams_for("series", &video_id, min_streams).await
+    }
 
-            match self.fetch_streams_from_addon(base_url, imdb_id).await {
-                Ok(mut streams) => {
-                    println!("[RUST] [STREAMS_FETCH] Found {} streams from torrent source {}", streams.len(), base_url);
-                    if !streams.is_empty() {
-                        successful_sources += 1;
-                        all_streams.append(&mut streams);
-                    }
-                }
-                Err(e) => {
-                    println!("[RUST] [STREAMS_FETCH] Failed to fetch streams from {}: {}", base_url, e);
-                    failed_sources.push(base_url.clone());
-                    continue;
-                }
-            }
+    // Same as `fetch_streams`/`fetch_episode_streams`, but reports every addon's
+    // streams to `on_progress` as soon as it answers
+    pub async fn fetch_streams_progressive(
+        &self,
+        imdb_id: &str,
+        season: Option<u32>,
+        episode: Option<u32>,
+        on_progress: impl FnMut(StreamsProgress),
+    ) -> Result<StreamsComplete, DeckflixError> {
+        let (content_type, video_id) = match (season, episode) {
+            (Some(season), Some(episode)) => ("series", format!("{}:{}:{}", imdb_id, season, episode)),
+            (None, None) => ("movie", imdb_id.to_string()),
+            _ => return Err(DeckflixError::invalid_request("Both season and episode are required for episode streams")),
+        };
+
+        let addons = self.addons_for("stream", content_type, Some(&video_id));
+        let (streams, sources) = self.collect_streams(&addons, content_type, &video_id, None, on_progress).await;
+
+        Ok(StreamsComplete {
+            video_id,
+            sources,
+            total_streams: streams.len(),
+        })
+    }
+
+    // With `min_streams` set, returns as soon as that many streams have arrived
+    // instead of waiting for the slowest addon
+    async fn fetch_streams_for(
+        &self,
+        content_type: &str,
+        video_id: &str,
+        min_streams: Option<usize>,
+    ) -> Result<Vec<Stream>, DeckflixError> {
+        // Only addons declaring the stream resource for this id are queried
+        let addons = self.addons_for("stream", content_type, Some(video_id));
+        if addons.is_empty() {
+            return Err(DeckflixError::no_streams("No installed addon provides streams for this content"));
         }
 
-        println!("[RUST] [STREAMS_FETCH] Stream fetching summary: {} successful sources, {} failed sources",
-                successful_sources, failed_sources.len());
+        let (all_streams, sources) = self.collect_streams(&addons, content_type, video_id, min_streams, |_| {}).await;
 
         if all_streams.is_empty() {
-            let error_msg = if failed_sources.len() == self.base_urls.len() - 1 { // -1 for cinemeta
-                format!("No streaming sources available. All torrent addons failed: {:?}", failed_sources)
-            } else {
-                "No streams found for this content from any torrent source".to_string()
-            };
-            return Err(error_msg);
-        }
+            let failed_sources: Vec<&StreamSourceStatus> = sources.iter().filter(|s| !s.success).collect();
+
+            // When every addon failed, the last failure says more than "no streams"
+            if failed_sources.len() == addons.len() {
+                println!("[RUST] [STREAMS_FETCH] All torrent addons failed: {:?}",
+                        failed_sources.iter().map(|s| &s.addon_name).collect::<Vec<_>>());
+                if let Some(error) = failed_sources.last().and_then(|s| s.error.clone()) {
+                    return Err(error);
+                }
+            }
 
-        // Sort streams by quality score (best first)
-        all_streams.sort_by(|a, b| {
-            self.calculate_stream_quality_score(b).partial_cmp(&self.calculate_stream_quality_score(a))
-                .unwrap_or(std::cmp::Ordering::Equal)
-        });
+            return Err(DeckflixError::no_streams("No streams found for this content from any torrent source"));
+        }
 
         println!("[RUST] [STREAMS_FETCH] Returning {} total streams", all_streams.len());
         Ok(all_streams)
     }
 
-    async fn fetch_movies_from_addon(
+    // Fans out to `addons` and keeps the merged streams ranked best first.
+    // Returns the ranking and the status of every addon that answered.
+    async fn collect_streams(
         &self,
-        base_url: &str,
-        catalog: &str,
-    ) -> Result<Vec<Movie>, String> {
-        let url = format!("{}/catalog/movie/{}.json", base_url, catalog);
-        println!("[RUST] [HTTP] Making request to correct Stremio endpoint: {}", url);
+        addons: &[Addon],
+        content_type: &str,
+        video_id: &str,
+        min_streams: Option<usize>,
+        mut on_progress: impl FnMut(StreamsProgress),
+    ) -> (Vec<Stream>, Vec<StreamSourceStatus>) {
+        println!("[RUST] [STREAMS_FETCH] Starting to fetch {} streams for video ID: {} from {} sources", content_type, video_id, addons.len());
+
+        let mut all_streams: Vec<Stream> = Vec::new();
+        // Positions in all_streams by stream_key
+        let mut positions: HashMap<String, Vec<usize>> = HashMap::new();
+        let mut sources = Vec::new();
+        let profile = self.active_ranking_profile();
+        println!("[RUST] [STREAMS_FETCH] Ranking with profile: {}", profile.name);
+
+        for addon in addons {
+            println!("[RUST] [STREAMS_FETCH] Querying torrent source: {}/stream/{}/{}.json", addon.base_url, content_type, video_id);
+        }
 
-        let start_time = std::time::Instant::now();
-        let response = self
-            .client
-            .get(&url)
-            .send()
-            .await
-            .map_err(|e| {
-                let error_msg = format!("Network error: {}", e);
-                println!("[RUST] [HTTP] ERROR: {}", error_msg);
-                error_msg
-            })?;
+        self.fan_out(addons, |addon| self.fetch_streams_from_addon(&addon.base_url, content_type, video_id), |addon, result| {
+            let (streams, error) = match result {
+                Ok(streams) => {
+                    println!("[RUST] [STREAMS_FETCH] Found {} streams from torrent source {}", streams.len(), addon.base_url);
+                    (streams, None)
+                }
+                Err(e) => {
+                    println!("[RUST] [STREAMS_FETCH] Failed to fetch streams from {}: {}", addon.base_url, e);
+                    (Vec::new(), Some(e))
+                }
+            };
 
-        let request_duration = start_time.elapsed();
-        println!("[RUST] [HTTP] Request completed in {:?}, status: {}", request_duration, response.status());
+            let source = StreamSourceStatus {
+                addon_id: addon.manifest.id.clone(),
+                addon_name: addon.name.clone(),
+                success: error.is_none(),
+                stream_count: streams.len(),
+                error,
+            };
 
-        if !response.status().is_success() {
-            let error_msg = format!("HTTP error: {}", response.status());
-            println!("[RUST] [HTTP] ERROR: {}", error_msg);
-            return Err(error_msg);
+            // The same torrent often comes back from several addons: merge instead of appending
+            let before = all_streams.len();
+            for stream in &streams {
+                let mut stream = stream.clone();
+                stream.addons = vec![addon.name.clone()];
+                self.merge_stream(&mut all_streams, &mut positions, stream);
+            }
+            let merged = before + streams.len() - all_streams.len();
+            if merged > 0 {
+                println!("[RUST] [STREAMS_FETCH] Merged {} duplicate streams from {}", merged, addon.name);
+            }
+
+            // Rank with the active profile (best first, blocked terms dropped)
+            all_streams = stream_ranking::rank(&profile, std::mem::take(&mut all_streams));
+            positions = self.stream_positions(&all_streams);
+
+            on_progress(StreamsProgress {
+                video_id: video_id.to_string(),
+                source: source.clone(),
+                streams,
+                ranking: all_streams.clone(),
+            });
+            sources.push(source);
+
+            match min_streams {
+                Some(min) if all_streams.len() >= min => {
+                    println!("[RUST] [STREAMS_FETCH] Collected {} streams (wanted {}), returning early", all_streams.len(), min);
+                    false
+                }
+                _ => true,
+            }
+        }).await;
+
+        let successful_sources = sources.iter().filter(|s| s.success && s.stream_count > 0).count();
+        let failed_sources = sources.iter().filter(|s| !s.success).count();
+        println!("[RUST] [STREAMS_FETCH] Stream fetching summary: {} successful sources, {} failed sources",
+                successful_sources, failed_sources);
+
+        (all_streams, sources)
+    }
+
+    pub async fn fetch_meta(&self, content_type: &str, id: &str) -> Result<MetaDetails, DeckflixError> {
+        println!("[RUST] [META_FETCH] Fetching {} meta for ID: {}", content_type, id);
+
+        let addons = self.addons_for("meta", content_type, Some(id));
+        if addons.is_empty() {
+            return Err(DeckflixError::not_found(format!("No installed addon provides {} meta for {}", content_type, id)));
         }
 
-        println!("[RUST] [HTTP] Parsing JSON response...");
-        let json_start = std::time::Instant::now();
-        let json: Value = response
-            .json()
-            .await
-            .map_err(|e| {
-                let error_msg = format!("JSON parse error: {}", e);
-                println!("[RUST] [HTTP] ERROR: {}", error_msg);
-                error_msg
-            })?;
+        let mut last_error = None;
+
+        // The first addon that answers wins
+        for base_url in addons.iter().map(|a| &a.base_url) {
+            match self.fetch_meta_from_addon(base_url, content_type, id).await {
+                Ok(details) => {
+                    println!("[RUST] [META_FETCH] Got meta for {} from {}: {} seasons, {} episodes",
+                            details.series.name, base_url,
+                            details.series.seasons.unwrap_or(0), details.series.episodes.unwrap_or(0));
+                    return Ok(details);
+                }
+                Err(e) => {
+                    println!("[RUST] [META_FETCH] ERROR: Failed to fetch meta from {}: {}", base_url, e);
+                    last_error = Some(e);
+                }
+            }
+        }
+
+        Err(last_error.unwrap_or_else(|| DeckflixError::not_found(format!("No meta found for {}", id))))
+    }
+
+    // GET a catalog/meta/search URL through the on-disk cache. Fresh entries skip
+    // the network, the addon's cacheMaxAge overrides the default TTL, and an
+    // expired entry is still served when the addon can't be reached.
+    async fn get_json_cached(&self, url: &str, ttl: Duration) -> Result<Value, DeckflixError> {
+        let cached = self.cache.get(url);
+        if let Some(entry) = &cached {
+            if entry.is_fresh() {
+                println!("[RUST] [CACHE] Hit ({:?} old): {}", entry.age(), url);
+                return Ok(entry.body.clone());
+            }
+        }
+
+        let result = match &cached {
+            Some(_) => tokio::time::timeout(STALE_FALLBACK_TIMEOUT, self.get_json(url))
+                .await
+                .unwrap_or_else(|_| Err(DeckflixError::timeout(STALE_FALLBACK_TIMEOUT))),
+            None => self.get_json(url).await,
+        };
+
+        match result {
+            Ok(json) => {
+                let max_age = json
+                    .get("cacheMaxAge")
+                    .and_then(|v| v.as_u64())
+                    .map(Duration::from_secs)
+                    .unwrap_or(ttl);
+                if let Err(e) = self.cache.put(url, &json, max_age) {
+                    println!("[RUST] [CACHE] ⚠️  Failed to store {}: {}", url, e);
+                }
+                Ok(json)
+            }
+            Err(e) if e.is_retryable() => match cached {
+                Some(entry) => {
+                    println!("[RUST] [CACHE] Serving stale entry ({:?} old) for {}: {}", entry.age(), url, e);
+                    Ok(entry.body)
+                }
+                None => Err(e),
+            },
+            Err(e) => Err(e),
+        }
+    }
+
+    async fn get_json(&self, url: &str) -> Result<Value, DeckflixError> {
+        let response = self
+            .client
+            .get(url)
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            return Err(DeckflixError::http_status(response.status(), url));
+        }
+
+        Ok(response.json().await?)
+    }
+
+    async fn fetch_meta_from_addon(
+        &self,
+        base_url: &str,
+        content_type: &str,
+        id: &str,
+    ) -> Result<MetaDetails, DeckflixError> {
+        let url = format!("{}/meta/{}/{}.json", base_url, content_type, id);
+        println!("[RUST] [HTTP] Making meta request: {}", url);
+
+        let json = self.get_json_cached(&url, META_CACHE_TTL).await?;
+
+        let meta = json.get("meta").cloned().ok_or_else(|| DeckflixError::parse("Missing 'meta' field"))?;
+        let meta: MetaItem = serde_json::from_value(meta)?;
+        let seasons = self.group_seasons(&meta.videos);
+        let mut series = Series::from(meta);
+
+        // Specials (season 0) are not counted as a regular season
+        let regular_seasons: Vec<&Season> = seasons.iter().filter(|s| s.number > 0).collect();
+        if !regular_seasons.is_empty() {
+            series.seasons = Some(regular_seasons.len() as u32);
+            series.episodes = Some(regular_seasons.iter().map(|s| s.episodes.len() as u32).sum());
+        }
+
+        Ok(MetaDetails { series, seasons })
+    }
+
+    fn group_seasons(&self, videos: &[MetaVideo]) -> Vec<Season> {
+        let mut seasons: Vec<Season> = Vec::new();
+        for video in videos {
+            let episode = match video.to_episode() {
+                Some(episode) => episode,
+                None => {
+                    println!("[RUST] [PARSE] Skipping video without season/episode: {}", video.id);
+                    continue;
+                }
+            };
+
+            match seasons.iter_mut().find(|s| s.number == episode.season) {
+                Some(season) => season.episodes.push(episode),
+                None => seasons.push(Season {
+                    number: episode.season,
+                    episodes: vec![episode],
+                }),
+            }
+        }
+
+        // Regular seasons in order, specials last
+        seasons.sort_by_key(|s| (s.number == 0, s.number));
+        for season in &mut seasons {
+            season.episodes.sort_by_key(|e| e.episode);
+        }
 
-        let json_duration = json_start.elapsed();
-        println!("[RUST] [HTTP] JSON parsing completed in {:?}", json_duration);
+        seasons
+    }
+
+    async fn fetch_movies_from_addon(
+        &self,
+        base_url: &str,
+        catalog: &str,
+        extra: &CatalogExtra,
+    ) -> Result<Vec<Movie>, DeckflixError> {
+        let url = Self::catalog_url(base_url, "movie", catalog, extra);
+        println!("[RUST] [HTTP] Making request to correct Stremio endpoint: {}", url);
+
+        let start_time = std::time::Instant::now();
+        let json = self.get_json_cached(&url, CATALOG_CACHE_TTL).await.map_err(|e| {
+            println!("[RUST] [HTTP] ERROR: {}", e);
+            e
+        })?;
+
+        let request_duration = start_time.elapsed();
+        println!("[RUST] [HTTP] Response ready in {:?}", request_duration);
 
         // Log some details about the JSON structure
         if let Some(metas) = json.get("metas") {
@@ -336,7 +1220,7 @@ impl AddonClient {
 
         println!("[RUST] [PARSE] Parsing movies from JSON...");
         let parse_start = std::time::Instant::now();
-        let movies = self.parse_movies_from_json(json)?;
+        let movies = self.parse_metas_from_json(json)?;
         let parse_duration = parse_start.elapsed();
 
         println!("[RUST] [PARSE] Parsed {} movies in {:?}", movies.len(), parse_duration);
@@ -346,71 +1230,53 @@ impl AddonClient {
     async fn fetch_streams_from_addon(
         &self,
         base_url: &str,
-        imdb_id: &str,
-    ) -> Result<Vec<Stream>, String> {
-        let url = format!("{}/stream/movie/{}.json", base_url, imdb_id);
+        content_type: &str,
+        video_id: &str,
+    ) -> Result<Vec<Stream>, DeckflixError> {
+        let url = format!("{}/stream/{}/{}.json", base_url, content_type, video_id);
         
-        let response = self
-            .client
-            .get(&url)
-            .send()
-            .await
-            .map_err(|e| format!("Network error: {}", e))?;
-
-        if !response.status().is_success() {
-            return Err(format!("HTTP error: {}", response.status()));
-        }
-
-        let json: Value = response
-            .json()
-            .await
-            .map_err(|e| format!("JSON parse error: {}", e))?;
+        let json = self.get_json(&url).await?;
 
         let streams = self.parse_streams_from_json(json)?;
         Ok(streams)
     }
 
-    fn parse_movies_from_json(&self, json: Value) -> Result<Vec<Movie>, String> {
-        let metas = json
-            .get("metas")
-            .ok_or("Missing 'metas' field")?
-            .as_array()
-            .ok_or("'metas' is not an array")?;
+    // Catalog `metas` as Movie, Series, Anime or SearchResult. A malformed entry
+    // is skipped rather than failing the whole catalog.
+    fn parse_metas_from_json<T: From<MetaItem>>(&self, json: Value) -> Result<Vec<T>, DeckflixError> {
+        let metas = match json {
+            Value::Object(mut object) => object
+                .remove("metas")
+                .ok_or_else(|| DeckflixError::parse("Missing 'metas' field"))?,
+            _ => return Err(DeckflixError::parse("Catalog response is not an object")),
+        };
+        let metas = match metas {
+            Value::Array(metas) => metas,
+            _ => return Err(DeckflixError::parse("'metas' is not an array")),
+        };
 
-        let mut movies = Vec::new();
+        let mut items = Vec::new();
         for meta in metas {
-            if let Ok(movie) = self.parse_single_movie(meta) {
-                movies.push(movie);
+            match serde_json::from_value::<MetaItem>(meta) {
+                Ok(item) => items.push(T::from(item)),
+                Err(e) => println!("[RUST] [PARSE] Skipping meta: {}", e),
             }
         }
 
-        Ok(movies)
+        Ok(items)
     }
 
     async fn fetch_series_from_addon(
         &self,
         base_url: &str,
         catalog: &str,
-    ) -> Result<Vec<Series>, String> {
-        let url = format!("{}/catalog/series/{}.json", base_url, catalog);
-
-        let response = self
-            .client
-            .get(&url)
-            .send()
-            .await
-            .map_err(|e| format!("Network error: {}", e))?;
-
-        if !response.status().is_success() {
-            return Err(format!("HTTP error: {}", response.status()));
-        }
+        extra: &CatalogExtra,
+    ) -> Result<Vec<Series>, DeckflixError> {
+        let url = Self::catalog_url(base_url, "series", catalog, extra);
 
-        let json: Value = response
-            .json()
-            .await
-            .map_err(|e| format!("JSON parse error: {}", e))?;
+        let json = self.get_json_cached(&url, CATALOG_CACHE_TTL).await?;
 
-        let series = self.parse_series_from_json(json)?;
+        let series = self.parse_metas_from_json(json)?;
         Ok(series)
     }
 
@@ -418,27 +1284,13 @@ impl AddonClient {
         &self,
         base_url: &str,
         catalog: &str,
-    ) -> Result<Vec<Anime>, String> {
+    ) -> Result<Vec<Anime>, DeckflixError> {
         // Fetch anime from series catalog
-        let url = format!("{}/catalog/series/{}.json", base_url, catalog);
-
-        let response = self
-            .client
-            .get(&url)
-            .send()
-            .await
-            .map_err(|e| format!("Network error: {}", e))?;
-
-        if !response.status().is_success() {
-            return Err(format!("HTTP error: {}", response.status()));
-        }
+        let url = Self::catalog_url(base_url, "series", catalog, &CatalogExtra::new());
 
-        let json: Value = response
-            .json()
-            .await
-            .map_err(|e| format!("JSON parse error: {}", e))?;
+        let json = self.get_json_cached(&url, CATALOG_CACHE_TTL).await?;
 
-        let anime = self.parse_anime_from_json(json)?;
+        let anime = self.parse_metas_from_json(json)?;
         Ok(anime)
     }
 
@@ -446,28 +1298,14 @@ impl AddonClient {
         &self,
         base_url: &str,
         catalog: &str,
-    ) -> Result<Vec<Anime>, String> {
+    ) -> Result<Vec<Anime>, DeckflixError> {
         // Fetch from movie catalog and filter for anime
-        let url = format!("{}/catalog/movie/{}.json", base_url, catalog);
+        let url = Self::catalog_url(base_url, "movie", catalog, &CatalogExtra::new());
 
-        let response = self
-            .client
-            .get(&url)
-            .send()
-            .await
-            .map_err(|e| format!("Network error: {}", e))?;
-
-        if !response.status().is_success() {
-            return Err(format!("HTTP error: {}", response.status()));
-        }
-
-        let json: Value = response
-            .json()
-            .await
-            .map_err(|e| format!("JSON parse error: {}", e))?;
+        let json = self.get_json_cached(&url, CATALOG_CACHE_TTL).await?;
 
         // Parse as anime and filter for anime content only
-        let mut all_content = self.parse_anime_from_json(json)?;
+        let mut all_content: Vec<Anime> = self.parse_metas_from_json(json)?;
 
         // Filter to keep only actual anime movies based on keywords
         all_content.retain(|anime| {
@@ -488,34 +1326,42 @@ impl AddonClient {
         Ok(all_content)
     }
 
+    // Catalog items of any type, as previews for home rows
+    async fn fetch_catalog_items(
+        &self,
+        base_url: &str,
+        content_type: &str,
+        catalog: &str,
+        extra: &CatalogExtra,
+    ) -> Result<Vec<SearchResult>, DeckflixError> {
+        let url = Self::catalog_url(base_url, content_type, catalog, extra);
+
+        let json = self.get_json_cached(&url, CATALOG_CACHE_TTL).await?;
+
+        let mut items: Vec<SearchResult> = self.parse_metas_from_json(json)?;
+
+        // Catalog items have the catalog's type (the parser defaults missing types to "movie")
+        for item in &mut items {
+            item.content_type = content_type.to_string();
+        }
+
+        Ok(items)
+    }
+
     // Search movies specifically
     async fn search_movies_from_addon(
         &self,
         base_url: &str,
         query: &str,
-    ) -> Result<Vec<SearchResult>, String> {
-        let encoded_query = urlencoding::encode(query);
-        let url = format!("{}/catalog/movie/top/search={}.json", base_url, encoded_query);
+    ) -> Result<Vec<SearchResult>, DeckflixError> {
+        let search_extra = CatalogExtra::from([("search".to_string(), query.to_string())]);
+        let url = Self::catalog_url(base_url, "movie", "top", &search_extra);
 
         println!("[RUST] [SEARCH] Searching movies at correct endpoint: {}", url);
 
-        let response = self
-            .client
-            .get(&url)
-            .send()
-            .await
-            .map_err(|e| format!("Network error: {}", e))?;
-
-        if !response.status().is_success() {
-            return Err(format!("HTTP error: {}", response.status()));
-        }
-
-        let json: Value = response
-            .json()
-            .await
-            .map_err(|e| format!("JSON parse error: {}", e))?;
+        let json = self.get_json_cached(&url, SEARCH_CACHE_TTL).await?;
 
-        let mut results = self.parse_search_results_from_json(json)?;
+        let mut results: Vec<SearchResult> = self.parse_metas_from_json(json)?;
 
         // Set content type for movies
         for result in &mut results {
@@ -532,29 +1378,15 @@ impl AddonClient {
         &self,
         base_url: &str,
         query: &str,
-    ) -> Result<Vec<SearchResult>, String> {
-        let encoded_query = urlencoding::encode(query);
-        let url = format!("{}/catalog/series/top/search={}.json", base_url, encoded_query);
+    ) -> Result<Vec<SearchResult>, DeckflixError> {
+        let search_extra = CatalogExtra::from([("search".to_string(), query.to_string())]);
+        let url = Self::catalog_url(base_url, "series", "top", &search_extra);
 
         println!("[RUST] [SEARCH] Searching series at correct endpoint: {}", url);
 
-        let response = self
-            .client
-            .get(&url)
-            .send()
-            .await
-            .map_err(|e| format!("Network error: {}", e))?;
-
-        if !response.status().is_success() {
-            return Err(format!("HTTP error: {}", response.status()));
-        }
-
-        let json: Value = response
-            .json()
-            .await
-            .map_err(|e| format!("JSON parse error: {}", e))?;
+        let json = self.get_json_cached(&url, SEARCH_CACHE_TTL).await?;
 
-        let mut results = self.parse_search_results_from_json(json)?;
+        let mut results: Vec<SearchResult> = self.parse_metas_from_json(json)?;
 
         // Set content type for series
         for result in &mut results {
@@ -610,241 +1442,154 @@ impl AddonClient {
         false
     }
 
-    fn parse_series_from_json(&self, json: Value) -> Result<Vec<Series>, String> {
-        let metas = json
-            .get("metas")
-            .ok_or("Missing 'metas' field")?
+    fn parse_streams_from_json(&self, json: Value) -> Result<Vec<Stream>, DeckflixError> {
+        let streams = json
+            .get("streams")
+            .ok_or_else(|| DeckflixError::parse("Missing 'streams' field"))?
             .as_array()
-            .ok_or("'metas' is not an array")?;
+            .ok_or_else(|| DeckflixError::parse("'streams' is not an array"))?;
+
+        // FileIdx 0 is a file like any other; the torrent streamer checks each
+        // fileIdx against the torrent's real file list once its metadata is known
+        Ok(streams
+            .iter()
+            .filter_map(|stream| self.parse_single_stream(stream).ok())
+            .collect())
+    }
 
-        let mut series = Vec::new();
-        for meta in metas {
-            if let Ok(parsed_series) = self.parse_single_series(meta) {
-                series.push(parsed_series);
-            }
+    // Identity of a stream across addons: infoHash for torrents (see same_file), normalized URL otherwise
+    fn stream_key(&self, stream: &Stream) -> String {
+        if let Some(info_hash) = &stream.info_hash {
+            return format!("btih:{}", info_hash);
         }
 
-        Ok(series)
-    }
-
-    fn parse_anime_from_json(&self, json: Value) -> Result<Vec<Anime>, String> {
-        let metas = json
-            .get("metas")
-            .ok_or("Missing 'metas' field")?
-            .as_array()
-            .ok_or("'metas' is not an array")?;
+        let url = stream.url.as_deref().unwrap_or_default().trim();
 
-        let mut anime = Vec::new();
-        for meta in metas {
-            if let Ok(parsed_anime) = self.parse_single_anime(meta) {
-                anime.push(parsed_anime);
+        // Scheme and host are case-insensitive; fragments and trailing slashes don't change the file
+        let url = url.split('#').next().unwrap_or(url).trim_end_matches('/');
+        match url.split_once("://") {
+            Some((scheme, rest)) => {
+                let (host, path) = rest.split_once('/').map(|(h, p)| (h, format!("/{}", p))).unwrap_or((rest, String::new()));
+                format!("{}://{}{}", scheme.to_lowercase(), host.to_lowercase(), path)
             }
+            None => url.to_string(),
         }
-
-        Ok(anime)
     }
 
-    fn parse_search_results_from_json(&self, json: Value) -> Result<Vec<SearchResult>, String> {
-        let metas = json
-            .get("metas")
-            .ok_or("Missing 'metas' field")?
-            .as_array()
-            .ok_or("'metas' is not an array")?;
-
-        let mut results = Vec::new();
-        for meta in metas {
-            if let Ok(result) = self.parse_single_search_result(meta) {
-                results.push(result);
-            }
+    // Streams of one torrent are the same file unless both name different files;
+    // addons without fileIdx (e.g. TPB+) mean whichever file the others picked
+    fn same_file(a: &Stream, b: &Stream) -> bool {
+        match (a.file_idx, b.file_idx) {
+            (Some(a), Some(b)) => a == b,
+            _ => true,
         }
-
-        Ok(results)
     }
 
-    fn parse_single_movie(&self, meta: &Value) -> Result<Movie, String> {
-        let id = meta
-            .get("id")
-            .and_then(|v| v.as_str())
-            .ok_or("Missing movie id")?
-            .to_string();
-
-        let name = meta
-            .get("name")
-            .and_then(|v| v.as_str())
-            .ok_or("Missing movie name")?
-            .to_string();
-
-        let poster = meta
-            .get("poster")
-            .and_then(|v| v.as_str())
-            .map(|s| s.to_string());
-
-        let background = meta
-            .get("background")
-            .and_then(|v| v.as_str())
-            .map(|s| s.to_string());
-
-        let description = meta
-            .get("description")
-            .and_then(|v| v.as_str())
-            .map(|s| s.to_string());
-
-        let year = meta
-            .get("year")
-            .and_then(|v| v.as_str())
-            .map(|s| s.to_string());
-
-        let imdb_rating = meta
-            .get("imdbRating")
-            .and_then(|v| v.as_str())
-            .map(|s| s.to_string());
-
-        let genre = meta
-            .get("genre")
-            .and_then(|v| v.as_array())
-            .map(|arr| {
-                arr.iter()
-                    .filter_map(|v| v.as_str())
-                    .map(|s| s.to_string())
-                    .collect()
-            });
-
-        let content_type = meta
-            .get("type")
-            .and_then(|v| v.as_str())
-            .map(|s| s.to_string());
-
-        let director = meta
-            .get("director")
-            .and_then(|v| v.as_array())
-            .map(|arr| {
-                arr.iter()
-                    .filter_map(|v| v.as_str())
-                    .map(|s| s.to_string())
-                    .collect()
-            });
-
-        let cast = meta
-            .get("cast")
-            .and_then(|v| v.as_array())
-            .map(|arr| {
-                arr.iter()
-                    .filter_map(|v| v.as_str())
-                    .map(|s| s.to_string())
-                    .collect()
-            });
-
-        let runtime = meta
-            .get("runtime")
-            .and_then(|v| v.as_str())
-            .map(|s| s.to_string());
-
-        let country = meta
-            .get("country")
-            .and_then(|v| v.as_str())
-            .map(|s| s.to_string());
-
-        let language = meta
-            .get("language")
-            .and_then(|v| v.as_str())
-            .map(|s| s.to_string());
-
-        Ok(Movie {
-            id,
-            name,
-            poster,
-            background,
-            description,
-            year,
-            imdb_rating,
-            genre,
-            content_type,
-            director,
-            cast,
-            runtime,
-            country,
-            language,
-        })
+    fn stream_positions(&self, streams: &[Stream]) -> HashMap<String, Vec<usize>> {
+        let mut positions: HashMap<String, Vec<usize>> = HashMap::new();
+        for (position, stream) in streams.iter().enumerate() {
+            positions.entry(self.stream_key(stream)).or_default().push(position);
+        }
+        positions
     }
 
-    fn parse_streams_from_json(&self, json: Value) -> Result<Vec<Stream>, String> {
-        let streams = json
-            .get("streams")
-            .ok_or("Missing 'streams' field")?
-            .as_array()
-            .ok_or("'streams' is not an array")?;
-
-        let mut parsed_streams = Vec::new();
-
-        // First pass: collect all streams and group by infoHash to detect multi-file torrents
-        let mut info_hash_counts: std::collections::HashMap<String, Vec<u64>> = std::collections::HashMap::new();
+    // Adds `stream` to `streams`, or folds it into the stream it duplicates,
+    // keeping the richest metadata and every addon it came from. `positions`
+    // indexes `streams` by stream_key and is kept up to date.
+    fn merge_stream(&self, streams: &mut Vec<Stream>, positions: &mut HashMap<String, Vec<usize>>, stream: Stream) {
+        let same_key = positions.entry(self.stream_key(&stream)).or_default();
+        let existing = match same_key.iter().copied().find(|&position| Self::same_file(&streams[position], &stream)) {
+            Some(position) => &mut streams[position],
+            None => {
+                same_key.push(streams.len());
+                streams.push(stream);
+                return;
+            }
+        };
 
-        for stream in streams {
-            if let Some(info_hash) = stream.get("infoHash").and_then(|v| v.as_str()) {
-                if let Some(file_idx) = stream.get("fileIdx").and_then(|v| v.as_u64()) {
-                    info_hash_counts.entry(info_hash.to_string())
-                        .or_insert_with(Vec::new)
-                        .push(file_idx);
-                }
+        // The longer title usually carries the seeders/size/language details
+        if stream.title.len() > existing.title.len() {
+            existing.title = stream.title;
+            existing.release = stream.release;
+            existing.quality = stream.quality;
+        }
+        existing.seeders = existing.seeders.max(stream.seeders);
+        existing.leechers = existing.leechers.max(stream.leechers);
+        existing.name = existing.name.take().or(stream.name);
+        existing.size = existing.size.take().or(stream.size);
+        existing.language = existing.language.take().or(stream.language);
+        existing.behavior_hints = existing.behavior_hints.take().or(stream.behavior_hints);
+        existing.file_idx = existing.file_idx.or(stream.file_idx);
+
+        // Every addon's trackers help find peers
+        for source in stream.sources {
+            if !existing.sources.contains(&source) {
+                existing.sources.push(source);
             }
         }
 
-        // Second pass: parse streams, but skip FileIdx 0 if multiple files exist for same infoHash
-        for stream in streams {
-            let should_skip = if let Some(info_hash) = stream.get("infoHash").and_then(|v| v.as_str()) {
-                if let Some(file_idx) = stream.get("fileIdx").and_then(|v| v.as_u64()) {
-                    // If this infoHash has multiple files AND this is FileIdx 0, skip it
-                    if let Some(file_indices) = info_hash_counts.get(info_hash) {
-                        if file_indices.len() > 1 && file_idx == 0 {
-                            println!("[RUST] [STREAM_PARSE] ⚠️  Skipping FileIdx 0 from multi-file torrent (InfoHash: {}, total files: {})",
-                                     info_hash, file_indices.len());
-                            true
-                        } else {
-                            false
-                        }
-                    } else {
-                        false
-                    }
-                } else {
-                    false
-                }
-            } else {
-                false
-            };
-
-            if !should_skip {
-                if let Ok(parsed) = self.parse_single_stream(stream) {
-                    parsed_streams.push(parsed);
+        if let Some(subtitles) = stream.subtitles {
+            let merged = existing.subtitles.get_or_insert_with(Vec::new);
+            for subtitle in subtitles {
+                if !merged.contains(&subtitle) {
+                    merged.push(subtitle);
                 }
             }
         }
 
-        Ok(parsed_streams)
+        for addon in stream.addons {
+            if !existing.addons.contains(&addon) {
+                existing.addons.push(addon);
+            }
+        }
     }
 
-    fn parse_single_stream(&self, stream: &Value) -> Result<Stream, String> {
-        // Handle both direct URLs and torrent infoHash
-        let url = if let Some(direct_url) = stream.get("url").and_then(|v| v.as_str()) {
-            // Direct streaming URL
-            direct_url.to_string()
-        } else if let Some(info_hash) = stream.get("infoHash").and_then(|v| v.as_str()) {
-            // Torrent magnet link
-            let file_idx = stream.get("fileIdx").and_then(|v| v.as_u64()).unwrap_or(0);
+    fn parse_single_stream(&self, stream: &Value) -> Result<Stream, DeckflixError> {
+        let mut info_hash = match stream.get("infoHash").and_then(|v| v.as_str()) {
+            Some(hash) => Some(normalize_info_hash(hash).ok_or_else(|| DeckflixError::parse(format!("Invalid infoHash: {}", hash)))?),
+            None => None,
+        };
 
-            // Log the stream details to debug multi-file torrents
-            let title_preview = stream.get("title").and_then(|v| v.as_str()).unwrap_or("No title");
-            println!("[RUST] [STREAM_PARSE] InfoHash: {} | FileIdx: {} | Title: {}",
-                     info_hash, file_idx, title_preview);
+        let file_idx = stream
+            .get("fileIdx")
+            .and_then(|v| v.as_u64())
+            .map(|n| n as u32);
 
-            if file_idx > 0 {
-                format!("magnet:?xt=urn:btih:{}&tr=udp%3A%2F%2Ftracker.opentrackr.org%3A1337%2Fannounce&tr=udp%3A%2F%2Fopen.demonii.com%3A1337%2Fannounce&so={}", info_hash, file_idx - 1)
-            } else {
-                format!("magnet:?xt=urn:btih:{}&tr=udp%3A%2F%2Ftracker.opentrackr.org%3A1337%2Fannounce&tr=udp%3A%2F%2Fopen.demonii.com%3A1337%2Fannounce", info_hash)
+        let mut sources: Vec<String> = stream
+            .get("sources")
+            .and_then(|v| v.as_array())
+            .map(|arr| arr.iter().filter_map(|v| v.as_str()).map(|s| s.to_string()).collect())
+            .unwrap_or_default();
+
+        // Handle both direct URLs and torrent infoHash; torrents have no URL and
+        // are played by info hash
+        let direct_url = stream.get("url").and_then(|v| v.as_str());
+        let url = match (direct_url, &info_hash) {
+            // Some addons send a magnet link as the URL
+            (Some(magnet), None) if magnet.starts_with("magnet:") => {
+                let (hash, trackers) = parse_magnet(magnet)
+                    .ok_or_else(|| DeckflixError::parse("Magnet link without an infoHash"))?;
+                info_hash = Some(hash);
+                sources.extend(trackers);
+                None
             }
-        } else {
-            return Err("Missing stream url or infoHash".to_string());
+            (Some(direct_url), _) => Some(direct_url.to_string()),
+            (None, Some(_)) => None,
+            (None, None) => return Err(DeckflixError::parse("Missing stream url or infoHash")),
         };
 
+        if let Some(info_hash) = &info_hash {
+            if !sources.iter().any(|s| s.starts_with("tracker:")) {
+                let fallback_trackers = self.fallback_trackers.read().unwrap();
+                sources.extend(fallback_trackers.iter().map(|tracker| format!("tracker:{}", tracker)));
+            }
+
+            // Log the stream details to debug multi-file torrents
+            let title_preview = stream.get("title").and_then(|v| v.as_str()).unwrap_or("No title");
+            println!("[RUST] [STREAM_PARSE] InfoHash: {} | FileIdx: {:?} | Sources: {} | Title: {}",
+                     info_hash, file_idx, sources.len(), title_preview);
+        }
+
         let title = stream
             .get("title")
             .and_then(|v| v.as_str())
@@ -856,7 +1601,8 @@ impl AddonClient {
             .and_then(|v| v.as_str())
             .map(|s| s.to_string());
 
-        let quality = self.extract_quality_from_title(&title);
+        let release = release_parser::parse(&title);
+        let quality = release.quality_label();
 
         // Extract size from torrent title or size field
         let size = if let Some(size_field) = stream.get("size").and_then(|v| v.as_str()) {
@@ -894,6 +1640,17 @@ impl AddonClient {
             .and_then(|v| v.as_str())
             .map(|s| s.to_string());
 
+        let behavior_hints = match stream.get("behaviorHints") {
+            Some(hints) => match serde_json::from_value::<StreamBehaviorHints>(hints.clone()) {
+                Ok(hints) => Some(hints),
+                Err(e) => {
+                    println!("[RUST] [STREAM_PARSE] ⚠️  Ignoring malformed behaviorHints: {}", e);
+                    None
+                }
+            },
+            None => None,
+        };
+
         let subtitles = stream
             .get("subtitles")
             .and_then(|v| v.as_array())
@@ -908,466 +1665,22 @@ impl AddonClient {
             name,
             title,
             url,
-            behavior_hints: None,
+            info_hash,
+            file_idx,
+            sources,
+            behavior_hints,
             quality,
             size,
             seeders,
             leechers,
             source,
+            release,
+            addons: Vec::new(),
             language,
             subtitles,
         })
     }
 
-    fn parse_single_series(&self, meta: &Value) -> Result<Series, String> {
-        // Extract basic info (same as movies)
-        let id = meta
-            .get("id")
-            .and_then(|v| v.as_str())
-            .ok_or("Missing series id")?
-            .to_string();
-
-        let name = meta
-            .get("name")
-            .and_then(|v| v.as_str())
-            .ok_or("Missing series name")?
-            .to_string();
-
-        let poster = meta
-            .get("poster")
-            .and_then(|v| v.as_str())
-            .map(|s| s.to_string());
-
-        let background = meta
-            .get("background")
-            .and_then(|v| v.as_str())
-            .map(|s| s.to_string());
-
-        let description = meta
-            .get("description")
-            .and_then(|v| v.as_str())
-            .map(|s| s.to_string());
-
-        let year = meta
-            .get("year")
-            .and_then(|v| v.as_str())
-            .map(|s| s.to_string());
-
-        let imdb_rating = meta
-            .get("imdbRating")
-            .and_then(|v| v.as_str())
-            .map(|s| s.to_string());
-
-        let genre = meta
-            .get("genre")
-            .and_then(|v| v.as_array())
-            .map(|arr| {
-                arr.iter()
-                    .filter_map(|v| v.as_str())
-                    .map(|s| s.to_string())
-                    .collect()
-            });
-
-        let content_type = meta
-            .get("type")
-            .and_then(|v| v.as_str())
-            .map(|s| s.to_string());
-
-        let director = meta
-            .get("director")
-            .and_then(|v| v.as_array())
-            .map(|arr| {
-                arr.iter()
-                    .filter_map(|v| v.as_str())
-                    .map(|s| s.to_string())
-                    .collect()
-            });
-
-        let cast = meta
-            .get("cast")
-            .and_then(|v| v.as_array())
-            .map(|arr| {
-                arr.iter()
-                    .filter_map(|v| v.as_str())
-                    .map(|s| s.to_string())
-                    .collect()
-            });
-
-        let runtime = meta
-            .get("runtime")
-            .and_then(|v| v.as_str())
-            .map(|s| s.to_string());
-
-        let country = meta
-            .get("country")
-            .and_then(|v| v.as_str())
-            .map(|s| s.to_string());
-
-        let language = meta
-            .get("language")
-            .and_then(|v| v.as_str())
-            .map(|s| s.to_string());
-
-        // Series-specific fields
-        let seasons = meta
-            .get("seasons")
-            .and_then(|v| v.as_u64())
-            .map(|n| n as u32);
-
-        let episodes = meta
-            .get("episodes")
-            .and_then(|v| v.as_u64())
-            .map(|n| n as u32);
-
-        let status = meta
-            .get("status")
-            .and_then(|v| v.as_str())
-            .map(|s| s.to_string());
-
-        let network = meta
-            .get("network")
-            .and_then(|v| v.as_str())
-            .map(|s| s.to_string());
-
-        Ok(Series {
-            id,
-            name,
-            poster,
-            background,
-            description,
-            year,
-            imdb_rating,
-            genre,
-            content_type,
-            director,
-            cast,
-            runtime,
-            country,
-            language,
-            seasons,
-            episodes,
-            status,
-            network,
-        })
-    }
-
-    fn parse_single_anime(&self, meta: &Value) -> Result<Anime, String> {
-        // Extract basic info (same as series)
-        let id = meta
-            .get("id")
-            .and_then(|v| v.as_str())
-            .ok_or("Missing anime id")?
-            .to_string();
-
-        let name = meta
-            .get("name")
-            .and_then(|v| v.as_str())
-            .ok_or("Missing anime name")?
-            .to_string();
-
-        let poster = meta
-            .get("poster")
-            .and_then(|v| v.as_str())
-            .map(|s| s.to_string());
-
-        let background = meta
-            .get("background")
-            .and_then(|v| v.as_str())
-            .map(|s| s.to_string());
-
-        let description = meta
-            .get("description")
-            .and_then(|v| v.as_str())
-            .map(|s| s.to_string());
-
-        let year = meta
-            .get("year")
-            .and_then(|v| v.as_str())
-            .map(|s| s.to_string());
-
-        let imdb_rating = meta
-            .get("imdbRating")
-            .and_then(|v| v.as_str())
-            .map(|s| s.to_string());
-
-        let genre = meta
-            .get("genre")
-            .and_then(|v| v.as_array())
-            .map(|arr| {
-                arr.iter()
-                    .filter_map(|v| v.as_str())
-                    .map(|s| s.to_string())
-                    .collect()
-            });
-
-        let content_type = meta
-            .get("type")
-            .and_then(|v| v.as_str())
-            .map(|s| s.to_string());
-
-        let director = meta
-            .get("director")
-            .and_then(|v| v.as_array())
-            .map(|arr| {
-                arr.iter()
-                    .filter_map(|v| v.as_str())
-                    .map(|s| s.to_string())
-                    .collect()
-            });
-
-        let cast = meta
-            .get("cast")
-            .and_then(|v| v.as_array())
-            .map(|arr| {
-                arr.iter()
-                    .filter_map(|v| v.as_str())
-                    .map(|s| s.to_string())
-                    .collect()
-            });
-
-        let runtime = meta
-            .get("runtime")
-            .and_then(|v| v.as_str())
-            .map(|s| s.to_string());
-
-        let country = meta
-            .get("country")
-            .and_then(|v| v.as_str())
-            .map(|s| s.to_string());
-
-        let language = meta
-            .get("language")
-            .and_then(|v| v.as_str())
-            .map(|s| s.to_string());
-
-        // Anime-specific fields
-        let seasons = meta
-            .get("seasons")
-            .and_then(|v| v.as_u64())
-            .map(|n| n as u32);
-
-        let episodes = meta
-            .get("episodes")
-            .and_then(|v| v.as_u64())
-            .map(|n| n as u32);
-
-        let status = meta
-            .get("status")
-            .and_then(|v| v.as_str())
-            .map(|s| s.to_string());
-
-        let studio = meta
-            .get("studio")
-            .and_then(|v| v.as_str())
-            .map(|s| s.to_string());
-
-        let mal_rating = meta
-            .get("malRating")
-            .and_then(|v| v.as_str())
-            .map(|s| s.to_string());
-
-        let anime_type = meta
-            .get("animeType")
-            .and_then(|v| v.as_str())
-            .map(|s| s.to_string());
-
-        Ok(Anime {
-            id,
-            name,
-            poster,
-            background,
-            description,
-            year,
-            imdb_rating,
-            genre,
-            content_type,
-            director,
-            cast,
-            runtime,
-            country,
-            language,
-            seasons,
-            episodes,
-            status,
-            studio,
-            mal_rating,
-            anime_type,
-        })
-    }
-
-    fn parse_single_search_result(&self, meta: &Value) -> Result<SearchResult, String> {
-        let id = meta
-            .get("id")
-            .and_then(|v| v.as_str())
-            .ok_or("Missing search result id")?
-            .to_string();
-
-        let name = meta
-            .get("name")
-            .and_then(|v| v.as_str())
-            .ok_or("Missing search result name")?
-            .to_string();
-
-        let poster = meta
-            .get("poster")
-            .and_then(|v| v.as_str())
-            .map(|s| s.to_string());
-
-        let year = meta
-            .get("year")
-            .and_then(|v| v.as_str())
-            .map(|s| s.to_string());
-
-        let imdb_rating = meta
-            .get("imdbRating")
-            .and_then(|v| v.as_str())
-            .map(|s| s.to_string());
-
-        let content_type = meta
-            .get("type")
-            .and_then(|v| v.as_str())
-            .unwrap_or("movie")
-            .to_string();
-
-        let description = meta
-            .get("description")
-            .and_then(|v| v.as_str())
-            .map(|s| s.to_string());
-
-        Ok(SearchResult {
-            id,
-            name,
-            poster,
-            year,
-            imdb_rating,
-            content_type,
-            description,
-        })
-    }
-
-    fn extract_quality_from_title(&self, title: &str) -> Option<String> {
-        let title_upper = title.to_uppercase();
-
-        // Enhanced quality patterns for torrent sources (ordered by preference)
-        let quality_patterns = [
-            // 4K variants
-            ("2160P", 6),
-            ("4K", 6),
-            ("UHD", 6),
-            // High quality 1080p variants
-            ("1080P BLURAY", 5),
-            ("1080P REMUX", 5),
-            ("1080P", 4),
-            // 720p variants
-            ("720P BLURAY", 4),
-            ("720P", 3),
-            // Other formats
-            ("BLURAY", 4),
-            ("WEBDL", 3),
-            ("WEBRIP", 3),
-            ("HDRIP", 3),
-            ("BRRIP", 3),
-            ("480P", 2),
-            ("DVDRIP", 2),
-            // Low quality
-            ("CAM", 1),
-            ("TS", 1),
-            ("HDTS", 1),
-            ("SCREENER", 1),
-        ];
-
-        // Find the best matching quality indicator
-        for (pattern, _score) in &quality_patterns {
-            if title_upper.contains(pattern) {
-                return Some(pattern.to_string());
-            }
-        }
-
-        // Check for resolution patterns like "x264", "x265", "H264", "H265"
-        if title_upper.contains("X265") || title_upper.contains("H265") || title_upper.contains("HEVC") {
-            return Some("H265".to_string());
-        }
-        if title_upper.contains("X264") || title_upper.contains("H264") {
-            return Some("H264".to_string());
-        }
-
-        None
-    }
-
-    fn calculate_stream_quality_score(&self, stream: &Stream) -> f64 {
-        let mut score = 0.0;
-
-        // Enhanced quality score based on torrent stream quality indicators
-        if let Some(quality) = &stream.quality {
-            score += match quality.as_str() {
-                // 4K and UHD content
-                "4K" | "2160P" | "UHD" => 60.0,
-                // High quality 1080p
-                "1080P BLURAY" | "1080P REMUX" => 55.0,
-                "1080P" => 45.0,
-                // 720p variants
-                "720P BLURAY" => 40.0,
-                "720P" => 35.0,
-                // Good quality sources
-                "BLURAY" => 42.0,
-                "WEBDL" => 30.0,
-                "WEBRIP" | "HDRIP" | "BRRIP" => 25.0,
-                // Standard definition
-                "480P" | "DVDRIP" => 15.0,
-                // Encoding quality bonus
-                "H265" | "X265" | "HEVC" => 35.0,
-                "H264" | "X264" => 30.0,
-                // Low quality
-                "CAM" | "TS" | "HDTS" | "SCREENER" => 5.0,
-                _ => 20.0,
-            };
-        }
-
-        // Seeders/leechers ratio (for torrents)
-        if let (Some(seeders), Some(leechers)) = (stream.seeders, stream.leechers) {
-            if leechers > 0 {
-                let ratio = seeders as f64 / leechers as f64;
-                score += ratio.min(10.0); // Cap ratio bonus at 10
-            } else if seeders > 0 {
-                score += 10.0; // No leechers but has seeders
-            }
-        }
-
-        // Size preference (reasonable file sizes get bonus)
-        if let Some(size_str) = &stream.size {
-            if let Ok(size_gb) = self.parse_size_to_gb(size_str) {
-                // Prefer sizes between 1-8GB for movies, penalize very small or very large
-                if size_gb >= 1.0 && size_gb <= 8.0 {
-                    score += 5.0;
-                } else if size_gb > 8.0 && size_gb <= 15.0 {
-                    score += 2.0;
-                } else if size_gb < 0.5 {
-                    score -= 5.0; // Penalize very small files
-                }
-            }
-        }
-
-        score
-    }
-
-    fn parse_size_to_gb(&self, size_str: &str) -> Result<f64, std::num::ParseFloatError> {
-        let size_upper = size_str.to_uppercase();
-
-        if size_upper.contains("GB") {
-            size_upper.replace("GB", "").trim().parse()
-        } else if size_upper.contains("MB") {
-            let mb: f64 = size_upper.replace("MB", "").trim().parse()?;
-            Ok(mb / 1024.0)
-        } else if size_upper.contains("TB") {
-            let tb: f64 = size_upper.replace("TB", "").trim().parse()?;
-            Ok(tb * 1024.0)
-        } else {
-            // Assume bytes
-            let bytes: f64 = size_str.trim().parse()?;
-            Ok(bytes / (1024.0 * 1024.0 * 1024.0))
-        }
-    }
-
     fn extract_size_from_title(&self, title: &str) -> Option<String> {
         use regex::Regex;
 
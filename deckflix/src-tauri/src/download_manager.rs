@@ -0,0 +1,765 @@
+use crate::error::DeckflixError;
+use crate::http_cache::stable_hash;
+use crate::models::{Download, DownloadStatus, EpisodeRef, Stream};
+use crate::torrent_cache::normalize_info_hash;
+use crate::torrent_streamer::{file_pieces, have_pieces, magnet_link, select_file, torrent_files};
+use librqbit::api::TorrentIdOrHash;
+use librqbit::dht::PersistentDhtConfig;
+use librqbit::{AddTorrent, AddTorrentOptions, ManagedTorrent, Session, SessionOptions};
+use reqwest::header::{CONTENT_LENGTH, CONTENT_RANGE, RANGE};
+use reqwest::{Client, StatusCode};
+use std::collections::{HashMap, HashSet};
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use std::io::SeekFrom;
+use tokio::io::{AsyncReadExt, AsyncSeekExt, AsyncWriteExt};
+use tokio::sync::OnceCell;
+use tokio::task::JoinHandle;
+
+const LIBRARY_FILE: &str = "downloads.json";
+
+// Incoming peer connections for downloads, next to the streamer's ports (see
+// DownloadManager for why downloads have an engine of their own)
+const LISTEN_PORTS: Range<u16> = 6891..6901;
+
+// How long a torrent download may look for metadata before it fails
+const METADATA_TIMEOUT: Duration = Duration::from_secs(120);
+
+// How often progress is checked and reported
+const PROGRESS_INTERVAL: Duration = Duration::from_secs(1);
+
+// Saves streams for offline playback: torrents by info hash into
+// <downloads dir>/<info hash>/, direct streams into <downloads dir>/<id>/.
+// Every download is kept in a library that survives restarts; ones that were
+// running when the app closed pick up where they left off. Downloads of several
+// files of one torrent (e.g. a season pack) share it in the engine.
+//
+// Torrents run in an engine of their own rather than the streamer's. An engine
+// holds a torrent once, with one output folder and one file selection, but the
+// streamer writes into the evictable cache and removes the torrent when the
+// stream stops, while a download writes into the library and runs until it's
+// done. Streaming one episode of a pack while downloading others, the usual
+// case, would have each overwrite the other's folder and selection and remove
+// the torrent from under it. The price is a second listener and DHT node; the
+// engine is only started once a torrent download runs.
+pub struct DownloadManager {
+    downloads_dir: PathBuf,
+    state_dir: PathBuf,
+    library_path: PathBuf,
+    http: Client,
+    engine: OnceCell<Arc<Session>>,
+    library: Mutex<Vec<Download>>,
+    tasks: Mutex<HashMap<String, JoinHandle<()>>>,
+    // Serializes changes to which torrents and files the engine downloads
+    engine_changes: tokio::sync::Mutex<()>,
+    on_update: Box<dyn Fn(&Download) + Send + Sync>,
+}
+
+impl DownloadManager {
+    pub fn new(
+        data_dir: &Path,
+        downloads_dir: PathBuf,
+        state_dir: PathBuf,
+        on_update: impl Fn(&Download) + Send + Sync + 'static,
+    ) -> Self {
+        let http = Client::builder()
+            .connect_timeout(Duration::from_secs(10))
+            .build()
+            .expect("Failed to create HTTP client");
+
+        let library_path = data_dir.join(LIBRARY_FILE);
+        let mut library: Vec<Download> = match std::fs::read_to_string(&library_path) {
+            Ok(contents) => serde_json::from_str(&contents).unwrap_or_else(|e| {
+                println!("[RUST] [DOWNLOADS] ERROR: Failed to parse {}: {}, starting with an empty library", library_path.display(), e);
+                Vec::new()
+            }),
+            Err(_) => Vec::new(),
+        };
+        // Verification is redone from the start
+        for download in library.iter_mut().filter(|d| d.status == DownloadStatus::Verifying) {
+            download.status = DownloadStatus::Downloading;
+        }
+        for download in library.iter_mut() {
+            download.download_speed = 0;
+        }
+
+        println!("[RUST] [DOWNLOADS] Loaded {} downloads from {}", library.len(), library_path.display());
+        Self {
+            downloads_dir,
+            state_dir,
+            library_path,
+            http,
+            engine: OnceCell::new(),
+            library: Mutex::new(library),
+            tasks: Mutex::new(HashMap::new()),
+            engine_changes: tokio::sync::Mutex::new(()),
+            on_update: Box::new(on_update),
+        }
+    }
+
+    async fn engine(&self) -> Result<&Arc<Session>, DeckflixError> {
+        self.engine
+            .get_or_try_init(|| async {
+                println!("[RUST] [DOWNLOADS] 🚀 Starting download engine");
+                let options = SessionOptions {
+                    listen_port_range: Some(LISTEN_PORTS),
+                    enable_upnp_port_forwarding: false,
+                    dht_config: Some(PersistentDhtConfig {
+                        config_filename: Some(self.state_dir.join("dht.json")),
+                        ..Default::default()
+                    }),
+                    ..Default::default()
+                };
+                Session::new_with_opts(self.downloads_dir.clone(), options)
+                    .await
+                    .map_err(|e| DeckflixError::torrent(format!("Failed to start download engine: {:#}", e)))
+            })
+            .await
+    }
+
+    // Restarts the downloads that were running when the app last closed
+    pub fn resume_interrupted(self: &Arc<Self>) {
+        let ids: Vec<String> = self
+            .library
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|d| d.status == DownloadStatus::Downloading)
+            .map(|d| d.id.clone())
+            .collect();
+
+        for id in ids {
+            println!("[RUST] [DOWNLOADS] ▶️  Resuming interrupted download {}", id);
+            self.spawn(&id);
+        }
+    }
+
+    pub fn list(&self) -> Vec<Download> {
+        self.library.lock().unwrap().clone()
+    }
+
+    // Adds a stream to the library and starts downloading it, picking the file for
+    // `episode` from a season pack. Queuing one that is already in the library
+    // resumes it if it was paused or failed.
+    pub fn queue(
+        self: &Arc<Self>,
+        mut stream: Stream,
+        title: Option<String>,
+        episode: Option<EpisodeRef>,
+    ) -> Result<Download, DeckflixError> {
+        stream.info_hash = match stream.info_hash.take() {
+            Some(info_hash) => Some(
+                normalize_info_hash(&info_hash)
+                    .ok_or_else(|| DeckflixError::invalid_request(format!("Invalid infohash: {}", info_hash)))?,
+            ),
+            None => match stream.url.as_deref() {
+                Some(url) if url.starts_with("http://") || url.starts_with("https://") => None,
+                url => return Err(DeckflixError::invalid_request(format!("Can't download {}", url.unwrap_or("a stream without a URL")))),
+            },
+        };
+
+        let id = download_id(&stream, episode);
+        let existing = self
+            .library
+            .lock()
+            .unwrap()
+            .iter()
+            .find(|d| is_download_of(d, &stream, episode))
+            .cloned();
+        if let Some(existing) = existing {
+            return match existing.status {
+                DownloadStatus::Paused | DownloadStatus::Failed => self.resume(&existing.id),
+                _ => Ok(existing),
+            };
+        }
+
+        let download = Download {
+            id: id.clone(),
+            title: title.unwrap_or_else(|| stream.title.lines().next().unwrap_or("").to_string()),
+            stream,
+            episode,
+            status: DownloadStatus::Downloading,
+            file_index: None,
+            file_path: None,
+            total_bytes: None,
+            downloaded_bytes: 0,
+            download_speed: 0,
+            error: None,
+            added_at: now_secs(),
+            completed_at: None,
+        };
+        println!("[RUST] [DOWNLOADS] ⬇️  Queued {} ({})", download.title, id);
+
+        {
+            let mut library = self.library.lock().unwrap();
+            library.push(download.clone());
+            self.save(&library);
+        }
+        (self.on_update)(&download);
+        self.spawn(&id);
+        Ok(download)
+    }
+
+    pub async fn pause(&self, id: &str) -> Result<Download, DeckflixError> {
+        let download = self.get(id)?;
+        if download.status != DownloadStatus::Downloading {
+            return Err(DeckflixError::invalid_request(format!("Download {} is not running", id)));
+        }
+
+        self.stop_task(id);
+        let download = self.set_status(id, DownloadStatus::Paused)?;
+        if let Some(info_hash) = &download.stream.info_hash {
+            self.sync_torrent(info_hash).await?;
+        }
+        println!("[RUST] [DOWNLOADS] ⏸️  Paused {}", id);
+        Ok(download)
+    }
+
+    pub fn resume(self: &Arc<Self>, id: &str) -> Result<Download, DeckflixError> {
+        let download = self.get(id)?;
+        if !matches!(download.status, DownloadStatus::Paused | DownloadStatus::Failed) {
+            return Err(DeckflixError::invalid_request(format!("Download {} is not paused", id)));
+        }
+
+        let download = self
+            .update(id, |d| {
+                d.status = DownloadStatus::Downloading;
+                d.error = None;
+            })
+            .ok_or_else(|| DeckflixError::not_found(format!("Download not found: {}", id)))?;
+        self.spawn(id);
+        println!("[RUST] [DOWNLOADS] ▶️  Resumed {}", id);
+        Ok(download)
+    }
+
+    // Removes the download from the library, and its file from disk if asked to
+    pub async fn remove(&self, id: &str, delete_file: bool) -> Result<(), DeckflixError> {
+        self.stop_task(id);
+        let download = {
+            let mut library = self.library.lock().unwrap();
+            let index = library
+                .iter()
+                .position(|d| d.id == id)
+                .ok_or_else(|| DeckflixError::not_found(format!("Download not found: {}", id)))?;
+            let download = library.remove(index);
+            self.save(&library);
+            download
+        };
+
+        if let Some(info_hash) = &download.stream.info_hash {
+            self.sync_torrent(info_hash).await?;
+        }
+
+        if delete_file {
+            if let Some(path) = download.file_path.as_deref().map(PathBuf::from) {
+                for path in [partial_path(&path), path] {
+                    if path.exists() {
+                        std::fs::remove_file(&path)
+                            .map_err(|e| DeckflixError::storage(format!("Failed to remove {}: {}", path.display(), e)))?;
+                    }
+                }
+            }
+        }
+
+        println!("[RUST] [DOWNLOADS] 🗑️  Removed {}{}", id, if delete_file { " and its file" } else { "" });
+        Ok(())
+    }
+
+    // The downloaded file for a stream (and episode), when it has been fully downloaded
+    pub fn local_file(&self, stream: &Stream, episode: Option<EpisodeRef>) -> Option<PathBuf> {
+        let library = self.library.lock().unwrap();
+        library
+            .iter()
+            .filter(|d| d.status == DownloadStatus::Completed)
+            .find(|d| is_download_of(d, stream, episode))
+            .and_then(|d| d.file_path.as_deref().map(PathBuf::from))
+            .filter(|path| path.is_file())
+    }
+
+    fn get(&self, id: &str) -> Result<Download, DeckflixError> {
+        self.library
+            .lock()
+            .unwrap()
+            .iter()
+            .find(|d| d.id == id)
+            .cloned()
+            .ok_or_else(|| DeckflixError::not_found(format!("Download not found: {}", id)))
+    }
+
+    fn update(&self, id: &str, change: impl FnOnce(&mut Download)) -> Option<Download> {
+        let download = {
+            let mut library = self.library.lock().unwrap();
+            let download = library.iter_mut().find(|d| d.id == id)?;
+            change(download);
+            let download = download.clone();
+            self.save(&library);
+            download
+        };
+        (self.on_update)(&download);
+        Some(download)
+    }
+
+    fn set_status(&self, id: &str, status: DownloadStatus) -> Result<Download, DeckflixError> {
+        self.update(id, |d| {
+            d.status = status;
+            d.download_speed = 0;
+            if status == DownloadStatus::Completed {
+                d.completed_at = Some(now_secs());
+            }
+        })
+        .ok_or_else(|| DeckflixError::not_found(format!("Download not found: {}", id)))
+    }
+
+    fn save(&self, library: &[Download]) {
+        let result = serde_json::to_string_pretty(library)
+            .map_err(|e| e.to_string())
+            .and_then(|json| {
+                if let Some(parent) = self.library_path.parent() {
+                    std::fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+                }
+                std::fs::write(&self.library_path, json).map_err(|e| e.to_string())
+            });
+        if let Err(e) = result {
+            println!("[RUST] [DOWNLOADS] ⚠️  Failed to save {}: {}", self.library_path.display(), e);
+        }
+    }
+
+    fn spawn(self: &Arc<Self>, id: &str) {
+        let manager = self.clone();
+        let task_id = id.to_string();
+        let task = tokio::spawn(async move {
+            let download = match manager.get(&task_id) {
+                Ok(download) => download,
+                Err(_) => return,
+            };
+            let result = match &download.stream.info_hash {
+                Some(info_hash) => manager.download_torrent(&task_id, info_hash).await,
+                None => manager.download_http(&task_id).await,
+            };
+
+            if let Err(e) = result {
+                println!("[RUST] [DOWNLOADS] ❌ Download {} failed: {}", task_id, e);
+                manager.update(&task_id, |d| {
+                    d.status = DownloadStatus::Failed;
+                    d.download_speed = 0;
+                    d.error = Some(e.message().to_string());
+                });
+                if let Some(info_hash) = &download.stream.info_hash {
+                    if let Err(e) = manager.sync_torrent(info_hash).await {
+                        println!("[RUST] [DOWNLOADS] ⚠️  {}", e);
+                    }
+                }
+            }
+            manager.tasks.lock().unwrap().remove(&task_id);
+        });
+
+        if let Some(previous) = self.tasks.lock().unwrap().insert(id.to_string(), task) {
+            previous.abort();
+        }
+    }
+
+    fn stop_task(&self, id: &str) {
+        if let Some(task) = self.tasks.lock().unwrap().remove(id) {
+            task.abort();
+        }
+    }
+
+    async fn download_torrent(&self, id: &str, info_hash: &str) -> Result<(), DeckflixError> {
+        let handle = self
+            .sync_torrent(info_hash)
+            .await?
+            .ok_or_else(|| DeckflixError::torrent("The torrent was removed from the download engine"))?;
+
+        let download = self.get(id)?;
+        let files = torrent_files(&handle)?;
+        let file = download
+            .file_index
+            .and_then(|idx| files.iter().find(|file| file.index == idx))
+            .or_else(|| select_file(&files, download.stream.file_idx, download.episode))
+            .ok_or_else(|| DeckflixError::torrent("Torrent contains no files"))?;
+        let (file_index, file_size) = (file.index, file.size);
+        let file_path = self.downloads_dir.join(info_hash).join(&file.path);
+
+        self.update(id, |d| {
+            d.file_index = Some(file_index);
+            d.file_path = Some(file_path.to_string_lossy().into_owned());
+            d.total_bytes = Some(file_size);
+        });
+        // Now that the file is known, only the files being downloaded are fetched
+        self.sync_torrent(info_hash).await?;
+        println!("[RUST] [DOWNLOADS] 📦 Downloading file #{} of {}: {}", file_index, info_hash, file.path);
+
+        loop {
+            let stats = handle.stats();
+            if let Some(error) = stats.error {
+                return Err(DeckflixError::torrent(error));
+            }
+            let downloaded_bytes = stats.file_progress.get(file_index).copied().unwrap_or(0);
+            let download_speed = stats
+                .live
+                .as_ref()
+                .map(|l| (l.download_speed.mbps * 1024.0 * 1024.0) as u64)
+                .unwrap_or(0);
+            self.update(id, |d| {
+                d.downloaded_bytes = downloaded_bytes;
+                d.download_speed = download_speed;
+            });
+            if downloaded_bytes >= file_size {
+                break;
+            }
+            tokio::time::sleep(PROGRESS_INTERVAL).await;
+        }
+
+        // The file's size on disk proves nothing, as the engine allocates it at full
+        // length up front; every piece of it must be hash-checked instead
+        self.set_status(id, DownloadStatus::Verifying)?;
+        verify_pieces(self.engine().await?, &handle, file_index)?;
+
+        self.set_status(id, DownloadStatus::Completed)?;
+        println!("[RUST] [DOWNLOADS] ✅ Completed {}", file_path.display());
+        self.sync_torrent(info_hash).await?;
+        Ok(())
+    }
+
+    // Brings the engine in line with the library for one torrent: added while any
+    // of its downloads runs, fetching only their files, and removed (keeping its
+    // data) once none do. Returns the torrent when it's in the engine.
+    async fn sync_torrent(&self, info_hash: &str) -> Result<Option<Arc<ManagedTorrent>>, DeckflixError> {
+        let engine = self.engine().await?;
+        let id = TorrentIdOrHash::try_from(info_hash)
+            .map_err(|e| DeckflixError::invalid_request(format!("Invalid infohash {}: {:#}", info_hash, e)))?;
+
+        let mut handle = engine.get(id);
+        if handle.is_none() {
+            let Some((sources, only_files)) = self.wanted_files(info_hash) else {
+                return Ok(None);
+            };
+            // Resolving metadata can take long, so this isn't done under engine_changes;
+            // a concurrent add of the same torrent just returns it
+            handle = Some(self.add_torrent(engine, info_hash, &sources, only_files).await?);
+        }
+        let Some(handle) = handle else {
+            return Ok(None);
+        };
+        handle
+            .wait_until_initialized()
+            .await
+            .map_err(|e| DeckflixError::torrent(format!("Failed to initialize torrent: {:#}", e)))?;
+
+        let _changes = self.engine_changes.lock().await;
+        match self.wanted_files(info_hash) {
+            None => {
+                println!("[RUST] [DOWNLOADS] Removing {} from the download engine", info_hash);
+                engine
+                    .delete(TorrentIdOrHash::Id(handle.id()), false)
+                    .await
+                    .map_err(|e| DeckflixError::torrent(format!("Failed to stop torrent: {:#}", e)))?;
+                Ok(None)
+            }
+            Some((_, only_files)) => {
+                if let Some(only_files) = only_files {
+                    if handle.only_files().map(|files| files.into_iter().collect::<HashSet<_>>()) != Some(only_files.clone()) {
+                        engine.update_only_files(&handle, &only_files).await.map_err(|e| {
+                            DeckflixError::torrent(format!("Failed to select files of {}: {:#}", info_hash, e))
+                        })?;
+                    }
+                }
+                Ok(Some(handle))
+            }
+        }
+    }
+
+    // Tracker sources and files of a torrent's running downloads, or None when
+    // none are running. Files are None while one download's file isn't known yet.
+    fn wanted_files(&self, info_hash: &str) -> Option<(Vec<String>, Option<HashSet<usize>>)> {
+        let library = self.library.lock().unwrap();
+        let running: Vec<&Download> = library
+            .iter()
+            .filter(|d| d.stream.info_hash.as_deref() == Some(info_hash))
+            .filter(|d| matches!(d.status, DownloadStatus::Downloading | DownloadStatus::Verifying))
+            .collect();
+        if running.is_empty() {
+            return None;
+        }
+
+        let mut sources: Vec<String> = running.iter().flat_map(|d| d.stream.sources.iter().cloned()).collect();
+        sources.sort();
+        sources.dedup();
+        let files = running.iter().map(|d| d.file_index).collect::<Option<HashSet<usize>>>();
+        Some((sources, files))
+    }
+
+    async fn add_torrent(
+        &self,
+        engine: &Arc<Session>,
+        info_hash: &str,
+        sources: &[String],
+        only_files: Option<HashSet<usize>>,
+    ) -> Result<Arc<ManagedTorrent>, DeckflixError> {
+        let magnet_link = magnet_link(info_hash, sources);
+        println!("[RUST] [DOWNLOADS] 🔍 Adding {} to the download engine", magnet_link);
+
+        let options = AddTorrentOptions {
+            // Existing data is checked and kept, so interrupted downloads resume
+            overwrite: true,
+            output_folder: Some(self.downloads_dir.join(info_hash).to_string_lossy().into_owned()),
+            only_files: only_files.map(|files| files.into_iter().collect()),
+            ..Default::default()
+        };
+        tokio::time::timeout(METADATA_TIMEOUT, engine.add_torrent(AddTorrent::from_url(magnet_link), Some(options)))
+            .await
+            .map_err(|_| DeckflixError::torrent(format!("No peers sent the torrent metadata within {} seconds", METADATA_TIMEOUT.as_secs())))?
+            .map_err(|e| DeckflixError::torrent(format!("Failed to add torrent: {:#}", e)))?
+            .into_handle()
+            .ok_or_else(|| DeckflixError::torrent("Download engine returned no handle"))
+    }
+
+    // Downloads into "<file>.part", continuing from its current length when the
+    // server supports ranges, and renames it once all of it has arrived
+    async fn download_http(&self, id: &str) -> Result<(), DeckflixError> {
+        let download = self.get(id)?;
+        let file_path = self.downloads_dir.join(id).join(http_file_name(&download.stream));
+        let part_path = partial_path(&file_path);
+        self.update(id, |d| d.file_path = Some(file_path.to_string_lossy().into_owned()));
+
+        if let Some(parent) = part_path.parent() {
+            tokio::fs::create_dir_all(parent)
+                .await
+                .map_err(|e| DeckflixError::storage(format!("Failed to create {}: {}", parent.display(), e)))?;
+        }
+        let mut offset = tokio::fs::metadata(&part_path).await.map(|m| m.len()).unwrap_or(0);
+
+        let url = download
+            .stream
+            .url
+            .clone()
+            .ok_or_else(|| DeckflixError::invalid_request("Direct download without a URL"))?;
+        let hints = download.stream.behavior_hints.clone().unwrap_or_default();
+        let mut request = self.http.get(&url);
+        for (name, value) in hints.request_headers() {
+            request = request.header(name, value);
+        }
+        if offset > 0 {
+            request = request.header(RANGE, format!("bytes={}-", offset));
+        }
+
+        println!("[RUST] [DOWNLOADS] ⬇️  Downloading {} from byte {}", url, offset);
+        let mut response = request.send().await?;
+        let total_bytes = match response.status() {
+            StatusCode::PARTIAL_CONTENT => content_range_total(&response),
+            // Everything was already downloaded
+            StatusCode::RANGE_NOT_SATISFIABLE if offset > 0 => Some(offset),
+            status if status.is_success() => {
+                // The server ignored the range; start over
+                offset = 0;
+                response
+                    .headers()
+                    .get(CONTENT_LENGTH)
+                    .and_then(|v| v.to_str().ok())
+                    .and_then(|v| v.parse().ok())
+            }
+            status => return Err(DeckflixError::http_status(status, &url)),
+        }
+        .or(hints.video_size);
+        self.update(id, |d| {
+            d.total_bytes = total_bytes;
+            d.downloaded_bytes = offset;
+        });
+
+        if response.status() != StatusCode::RANGE_NOT_SATISFIABLE {
+            let mut file = tokio::fs::OpenOptions::new()
+                .create(true)
+                .write(true)
+                .append(offset > 0)
+                .truncate(offset == 0)
+                .open(&part_path)
+                .await
+                .map_err(|e| DeckflixError::storage(format!("Failed to open {}: {}", part_path.display(), e)))?;
+
+            let mut last_report = Instant::now();
+            let mut bytes_since_report = 0u64;
+            while let Some(chunk) = response.chunk().await? {
+                file.write_all(&chunk)
+                    .await
+                    .map_err(|e| DeckflixError::storage(format!("Failed to write {}: {}", part_path.display(), e)))?;
+                offset += chunk.len() as u64;
+                bytes_since_report += chunk.len() as u64;
+
+                if last_report.elapsed() >= PROGRESS_INTERVAL {
+                    let download_speed = (bytes_since_report as f64 / last_report.elapsed().as_secs_f64()) as u64;
+                    self.update(id, |d| {
+                        d.downloaded_bytes = offset;
+                        d.download_speed = download_speed;
+                    });
+                    last_report = Instant::now();
+                    bytes_since_report = 0;
+                }
+            }
+            file.flush()
+                .await
+                .map_err(|e| DeckflixError::storage(format!("Failed to write {}: {}", part_path.display(), e)))?;
+        }
+
+        self.update(id, |d| d.downloaded_bytes = offset);
+        self.set_status(id, DownloadStatus::Verifying)?;
+        // Without a known size, a download that ended cleanly is taken as complete
+        verify_size(&part_path, total_bytes.unwrap_or(offset)).await?;
+        if let Some(video_hash) = &hints.video_hash {
+            if let Err(e) = verify_video_hash(&part_path, video_hash).await {
+                // A retry downloads it again instead of resuming the corrupt file
+                let _ = tokio::fs::remove_file(&part_path).await;
+                return Err(e);
+            }
+        }
+        tokio::fs::rename(&part_path, &file_path)
+            .await
+            .map_err(|e| DeckflixError::storage(format!("Failed to move {} into place: {}", file_path.display(), e)))?;
+
+        self.set_status(id, DownloadStatus::Completed)?;
+        println!("[RUST] [DOWNLOADS] ✅ Completed {}", file_path.display());
+        Ok(())
+    }
+}
+
+// Torrents by info hash and file (or episode, until the file is known), direct streams by URL
+fn download_id(stream: &Stream, episode: Option<EpisodeRef>) -> String {
+    match (&stream.info_hash, stream.file_idx, episode) {
+        (Some(info_hash), Some(file_idx), _) => format!("{}-{}", info_hash.to_lowercase(), file_idx),
+        (Some(info_hash), None, Some(episode)) => {
+            format!("{}-s{:02}e{:02}", info_hash.to_lowercase(), episode.season, episode.episode)
+        }
+        (Some(info_hash), None, None) => info_hash.to_lowercase(),
+        // Stored in downloads.json and used as a directory name, so it must not change between builds
+        (None, _, _) => format!("{:016x}", stable_hash(stream.url.as_deref().unwrap_or_default())),
+    }
+}
+
+// Whether a download is of the same file as a stream: for torrents the same file
+// index, or the same episode of a pack; a torrent without either only matches a
+// download that was queued without them too
+fn is_download_of(download: &Download, stream: &Stream, episode: Option<EpisodeRef>) -> bool {
+    match (&stream.info_hash, &download.stream.info_hash) {
+        (Some(hash), Some(downloaded)) if hash.eq_ignore_ascii_case(downloaded) => match (stream.file_idx, episode) {
+            (Some(idx), _) => download.file_index == Some(idx as usize) || download.stream.file_idx == Some(idx),
+            (None, Some(episode)) => download.episode == Some(episode),
+            (None, None) => download.stream.file_idx.is_none() && download.episode.is_none(),
+        },
+        (None, None) => stream.url == download.stream.url,
+        _ => false,
+    }
+}
+
+// The addon's file name, otherwise the last part of the URL path
+fn http_file_name(stream: &Stream) -> String {
+    let from_url = stream
+        .url
+        .as_deref()
+        .and_then(|url| url.split(['?', '#']).next())
+        .and_then(|path| path.rsplit('/').next())
+        .map(|name| urlencoding::decode(name).map(|n| n.into_owned()).unwrap_or_else(|_| name.to_string()));
+
+    stream
+        .behavior_hints
+        .as_ref()
+        .and_then(|hints| hints.filename.clone())
+        .or(from_url)
+        .map(|name| name.replace(['/', '\\'], "_"))
+        .filter(|name| !name.trim().is_empty())
+        .unwrap_or_else(|| "video".to_string())
+}
+
+fn partial_path(path: &Path) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(".part");
+    path.with_file_name(name)
+}
+
+// Total length from "Content-Range: bytes 100-199/1000"
+fn content_range_total(response: &reqwest::Response) -> Option<u64> {
+    response
+        .headers()
+        .get(CONTENT_RANGE)?
+        .to_str()
+        .ok()?
+        .rsplit('/')
+        .next()?
+        .parse()
+        .ok()
+}
+
+async fn verify_size(path: &Path, expected: u64) -> Result<(), DeckflixError> {
+    let actual = tokio::fs::metadata(path)
+        .await
+        .map_err(|e| DeckflixError::storage(format!("Failed to check {}: {}", path.display(), e)))?
+        .len();
+    if actual != expected {
+        return Err(DeckflixError::storage(format!(
+            "{} is {} bytes, expected {}",
+            path.display(),
+            actual,
+            expected
+        )));
+    }
+    Ok(())
+}
+
+fn verify_pieces(engine: &Arc<Session>, handle: &ManagedTorrent, file_index: usize) -> Result<(), DeckflixError> {
+    let pieces = file_pieces(handle, file_index)?;
+    let haves = have_pieces(engine, handle).ok_or_else(|| DeckflixError::torrent("The download engine didn't report its pieces"))?;
+    let missing = haves
+        .get(pieces.clone())
+        .map(|pieces| pieces.iter().filter(|have| !**have).count())
+        .unwrap_or(pieces.len());
+    if missing > 0 {
+        return Err(DeckflixError::torrent(format!(
+            "{} of {} pieces of file #{} aren't verified",
+            missing,
+            pieces.len(),
+            file_index
+        )));
+    }
+    Ok(())
+}
+
+// Checks the file against the addon's OpenSubtitles hash (behaviorHints.videoHash)
+async fn verify_video_hash(path: &Path, expected: &str) -> Result<(), DeckflixError> {
+    let actual = opensubtitles_hash(path)
+        .await
+        .map_err(|e| DeckflixError::storage(format!("Failed to hash {}: {}", path.display(), e)))?;
+    if !format!("{:016x}", actual).eq_ignore_ascii_case(expected.trim()) {
+        return Err(DeckflixError::storage(format!(
+            "{} has hash {:016x}, expected {}",
+            path.display(),
+            actual,
+            expected
+        )));
+    }
+    Ok(())
+}
+
+// The file size plus every little-endian 64-bit word of its first and last 64 KiB
+async fn opensubtitles_hash(path: &Path) -> std::io::Result<u64> {
+    const CHUNK_BYTES: u64 = 64 * 1024;
+    let mut file = tokio::fs::File::open(path).await?;
+    let size = file.metadata().await?.len();
+
+    let mut hash = size;
+    let mut chunk = vec![0u8; CHUNK_BYTES.min(size) as usize];
+    for start in [0, size - chunk.len() as u64] {
+        file.seek(SeekFrom::Start(start)).await?;
+        file.read_exact(&mut chunk).await?;
+        for word in chunk.chunks_exact(8) {
+            hash = hash.wrapping_add(u64::from_le_bytes(word.try_into().expect("8-byte chunk")));
+        }
+    }
+    Ok(hash)
+}
+
+fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
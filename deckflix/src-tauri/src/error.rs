@@ -0,0 +1,136 @@
+use crate::models::StartupFailure;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+// Error returned by every command. Serialized for the frontend as
+// {"kind": "http_status", "message": "...", ...} so the UI can pick a remedy
+// per kind and still show `error.message` like before.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum DeckflixError {
+    // Request never got a response (DNS, connection refused, timeout, ...)
+    Network { message: String, timed_out: bool },
+    // Server answered with a non-success status
+    HttpStatus { message: String, status: u16, url: String },
+    // Response or file content wasn't what we expected
+    Parse { message: String },
+    // Every addon answered but none had a stream
+    NoStreams { message: String },
+    // No video player could be started
+    PlayerLaunch { message: String },
+    // Catalog, meta or addon lookup found nothing
+    NotFound { message: String },
+    // The caller passed arguments we can't act on
+    InvalidRequest { message: String },
+    // Reading or writing app data on disk failed
+    Storage { message: String },
+    // A torrent stream didn't get to the point where it can be played
+    StreamStartup { message: String, reason: StartupFailure },
+    // Torrent streaming failed after it started
+    Torrent { message: String },
+}
+
+impl DeckflixError {
+    pub fn http_status(status: reqwest::StatusCode, url: &str) -> Self {
+        DeckflixError::HttpStatus {
+            message: format!("HTTP error: {}", status),
+            status: status.as_u16(),
+            url: url.to_string(),
+        }
+    }
+
+    pub fn timeout(after: std::time::Duration) -> Self {
+        DeckflixError::Network {
+            message: format!("Timed out after {:?}", after),
+            timed_out: true,
+        }
+    }
+
+    pub fn parse(message: impl Into<String>) -> Self {
+        DeckflixError::Parse { message: message.into() }
+    }
+
+    pub fn no_streams(message: impl Into<String>) -> Self {
+        DeckflixError::NoStreams { message: message.into() }
+    }
+
+    pub fn player_launch(message: impl Into<String>) -> Self {
+        DeckflixError::PlayerLaunch { message: message.into() }
+    }
+
+    pub fn not_found(message: impl Into<String>) -> Self {
+        DeckflixError::NotFound { message: message.into() }
+    }
+
+    pub fn invalid_request(message: impl Into<String>) -> Self {
+        DeckflixError::InvalidRequest { message: message.into() }
+    }
+
+    pub fn storage(message: impl Into<String>) -> Self {
+        DeckflixError::Storage { message: message.into() }
+    }
+
+    pub fn stream_startup(reason: StartupFailure, message: impl Into<String>) -> Self {
+        DeckflixError::StreamStartup {
+            message: message.into(),
+            reason,
+        }
+    }
+
+    pub fn torrent(message: impl Into<String>) -> Self {
+        DeckflixError::Torrent { message: message.into() }
+    }
+
+    pub fn message(&self) -> &str {
+        match self {
+            DeckflixError::Network { message, .. }
+            | DeckflixError::HttpStatus { message, .. }
+            | DeckflixError::Parse { message }
+            | DeckflixError::NoStreams { message }
+            | DeckflixError::PlayerLaunch { message }
+            | DeckflixError::NotFound { message }
+            | DeckflixError::InvalidRequest { message }
+            | DeckflixError::Storage { message }
+            | DeckflixError::StreamStartup { message, .. }
+            | DeckflixError::Torrent { message } => message,
+        }
+    }
+
+    // Whether trying the same request again later could succeed
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            DeckflixError::Network { .. } => true,
+            DeckflixError::HttpStatus { status, .. } => *status == 429 || *status >= 500,
+            _ => false,
+        }
+    }
+}
+
+impl fmt::Display for DeckflixError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.message())
+    }
+}
+
+impl std::error::Error for DeckflixError {}
+
+impl From<reqwest::Error> for DeckflixError {
+    fn from(e: reqwest::Error) -> Self {
+        if e.is_decode() {
+            DeckflixError::parse(format!("JSON parse error: {}", e))
+        } else if let Some(status) = e.status() {
+            DeckflixError::http_status(status, e.url().map(|u| u.as_str()).unwrap_or(""))
+        } else {
+            DeckflixError::Network {
+                message: format!("Network error: {}", e),
+                timed_out: e.is_timeout(),
+            }
+        }
+    }
+}
+
+impl From<serde_json::Error> for DeckflixError {
+    fn from(e: serde_json::Error) -> Self {
+        DeckflixError::parse(format!("JSON parse error: {}", e))
+    }
+}
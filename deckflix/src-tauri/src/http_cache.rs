@@ -0,0 +1,135 @@
+use crate::error::DeckflixError;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::fs;
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+// Entries unused for this long are removed, expired or not
+const MAX_ENTRY_AGE: Duration = Duration::from_secs(30 * 24 * 60 * 60);
+
+// Above this, the least recently written entries are removed
+const MAX_CACHE_BYTES: u64 = 50 * 1024 * 1024;
+
+// One cached addon response, stored as <cache dir>/<url hash>.json
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CachedResponse {
+    pub url: String,
+    pub fetched_at: u64,
+    pub max_age: u64,
+    pub body: Value,
+}
+
+impl CachedResponse {
+    pub fn age(&self) -> Duration {
+        Duration::from_secs(now_secs().saturating_sub(self.fetched_at))
+    }
+
+    pub fn is_fresh(&self) -> bool {
+        self.age() < Duration::from_secs(self.max_age)
+    }
+}
+
+// On-disk cache of addon JSON responses keyed by URL. Entries are kept after
+// they expire so they can still be served when the network is unavailable,
+// until they are older than MAX_ENTRY_AGE or the cache outgrows MAX_CACHE_BYTES.
+pub struct ResponseCache {
+    dir: PathBuf,
+}
+
+impl ResponseCache {
+    pub fn new(dir: PathBuf) -> Self {
+        let cache = Self { dir };
+        cache.sweep();
+        cache
+    }
+
+    fn entry_path(&self, url: &str) -> PathBuf {
+        self.dir.join(format!("{:016x}.json", stable_hash(url)))
+    }
+
+    pub fn get(&self, url: &str) -> Option<CachedResponse> {
+        let contents = fs::read_to_string(self.entry_path(url)).ok()?;
+        match serde_json::from_str::<CachedResponse>(&contents) {
+            // Guard against hash collisions
+            Ok(entry) if entry.url == url => Some(entry),
+            Ok(_) => None,
+            Err(e) => {
+                println!("[RUST] [CACHE] ⚠️  Ignoring corrupt cache entry for {}: {}", url, e);
+                None
+            }
+        }
+    }
+
+    pub fn put(&self, url: &str, body: &Value, max_age: Duration) -> Result<(), DeckflixError> {
+        fs::create_dir_all(&self.dir)
+            .map_err(|e| DeckflixError::storage(format!("Failed to create {}: {}", self.dir.display(), e)))?;
+
+        let entry = CachedResponse {
+            url: url.to_string(),
+            fetched_at: now_secs(),
+            max_age: max_age.as_secs(),
+            body: body.clone(),
+        };
+        let path = self.entry_path(url);
+        let contents = serde_json::to_string(&entry)?;
+        fs::write(&path, contents)
+            .map_err(|e| DeckflixError::storage(format!("Failed to write {}: {}", path.display(), e)))
+    }
+
+    // Removes old entries, then the oldest ones while the cache is too big
+    fn sweep(&self) {
+        let Ok(dir) = fs::read_dir(&self.dir) else {
+            return;
+        };
+        let mut entries: Vec<(PathBuf, SystemTime, u64)> = dir
+            .flatten()
+            .filter_map(|entry| {
+                let metadata = entry.metadata().ok()?;
+                Some((entry.path(), metadata.modified().ok()?, metadata.len()))
+            })
+            .collect();
+        // Newest first, so the oldest are popped
+        entries.sort_by_key(|(_, modified, _)| std::cmp::Reverse(*modified));
+
+        let mut total: u64 = entries.iter().map(|(_, _, size)| size).sum();
+        let mut removed = 0;
+        while let Some((path, modified, size)) = entries.pop() {
+            let too_old = modified.elapsed().is_ok_and(|age| age > MAX_ENTRY_AGE);
+            if !too_old && total <= MAX_CACHE_BYTES {
+                break;
+            }
+            match fs::remove_file(&path) {
+                Ok(()) => removed += 1,
+                Err(e) => println!("[RUST] [CACHE] ⚠️  Failed to remove {}: {}", path.display(), e),
+            }
+            total = total.saturating_sub(size);
+        }
+        if removed > 0 {
+            println!("[RUST] [CACHE] 🧹 Removed {} old cache entries ({} bytes left)", removed, total);
+        }
+    }
+
+    pub fn clear(&self) -> Result<(), DeckflixError> {
+        if !self.dir.exists() {
+            return Ok(());
+        }
+        fs::remove_dir_all(&self.dir)
+            .map_err(|e| DeckflixError::storage(format!("Failed to clear {}: {}", self.dir.display(), e)))
+    }
+}
+
+// 64-bit FNV-1a. Unlike DefaultHasher it is the same in every build, so it can
+// name files that outlive the app version.
+pub fn stable_hash(text: &str) -> u64 {
+    text.bytes().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
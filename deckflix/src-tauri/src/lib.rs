@@ -1,5 +1,14 @@
+pub mod error;
 pub mod models;
+pub mod http_cache;
+pub mod release_parser;
+pub mod stream_ranking;
 pub mod addon_client;
+pub mod download_manager;
+pub mod stream_server;
+pub mod stream_session;
+pub mod stream_stats;
+pub mod torrent_cache;
 pub mod torrent_streamer;
 
 // Learn more about Tauri commands at https://tauri.app/develop/calling-rust/
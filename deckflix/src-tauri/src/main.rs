@@ -1,173 +1,186 @@
 // Prevents additional console window on Windows in release, DO NOT REMOVE!!
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
+mod error;
 mod models;
+mod http_cache;
+mod release_parser;
+mod stream_ranking;
 mod addon_client;
+mod download_manager;
+mod stream_server;
+mod stream_session;
+mod stream_stats;
+mod torrent_cache;
 mod torrent_streamer;
 
 use addon_client::AddonClient;
+use download_manager::DownloadManager;
+use error::DeckflixError;
+use torrent_cache::TorrentCache;
 use torrent_streamer::TorrentStreamer;
-use models::{Movie, Series, Anime, Stream, SearchResult};
-use tauri::{State, Manager};
+use models::{Movie, Series, Anime, Stream, StreamBehaviorHints, StreamsComplete, SearchResult, MetaDetails, Addon, CatalogExtra, HomeRow, HomeRowSetting, RankingProfile, RankingSettings, StreamStats, Download, EpisodeRef, TorrentFileList, TorrentCacheSettings, TorrentCacheStatus};
+use tauri::{State, Manager, Emitter};
 use tauri_plugin_shell::ShellExt;
-use tokio::sync::Mutex;
 use std::sync::Arc;
 
-// Global state for the addon client and torrent streamer
+// Global state for the addon client, torrent streamer, torrent cache and downloads
 struct AppState {
-    client: Mutex<AddonClient>,
-    streamer: Arc<Mutex<TorrentStreamer>>,
+    client: AddonClient,
+    streamer: Arc<TorrentStreamer>,
+    torrent_cache: Arc<TorrentCache>,
+    downloads: Arc<DownloadManager>,
 }
 
 #[tauri::command]
-async fn fetch_popular_movies(state: State<'_, AppState>) -> Result<Vec<Movie>, String> {
-    let client = state.client.lock().await;
-    client.fetch_popular_movies().await
+async fn fetch_popular_movies(extra: Option<CatalogExtra>, state: State<'_, AppState>) -> Result<Vec<Movie>, DeckflixError> {
+    state.client.fetch_popular_movies(&extra.unwrap_or_default()).await
 }
 
 #[tauri::command]
-async fn fetch_streams(imdb_id: String, state: State<'_, AppState>) -> Result<Vec<Stream>, String> {
+async fn fetch_streams(
+    imdb_id: String,
+    season: Option<u32>,
+    episode: Option<u32>,
+    min_streams: Option<usize>,
+    state: State<'_, AppState>
+) -> Result<Vec<Stream>, DeckflixError> {
     println!("╔════════════════════════════════════════════════════════════════════");
     println!("║ [RUST] [FETCH_STREAMS_COMMAND] Tauri command called from JavaScript");
     println!("║ [RUST] [FETCH_STREAMS_COMMAND] Received IMDB ID: {}", imdb_id);
     println!("║ [RUST] [FETCH_STREAMS_COMMAND] ID Length: {}", imdb_id.len());
+    println!("║ [RUST] [FETCH_STREAMS_COMMAND] Season: {:?}, Episode: {:?}", season, episode);
     println!("╚════════════════════════════════════════════════════════════════════");
 
-    let client = state.client.lock().await;
-    client.fetch_streams(&imdb_id).await
+    let client = &state.client;
+    match (season, episode) {
+        (Some(season), Some(episode)) => client.fetch_episode_streams(&imdb_id, season, episode, min_streams).await,
+        (None, None) => client.fetch_streams(&imdb_id, min_streams).await,
+        _ => Err(DeckflixError::invalid_request("Both season and episode are required for episode streams")),
+    }
 }
 
+// Emits "streams-progress" as each addon answers and "streams-complete" at the end,
+// so the UI can show the first results while slower sources are still loading
 #[tauri::command]
-async fn play_video_external(
+async fn fetch_streams_progressive(
     app: tauri::AppHandle,
-    state: State<'_, AppState>,
-    stream_url: String
-) -> Result<String, String> {
-    println!("[RUST] [VIDEO_PLAYER] ==============================================");
-    println!("[RUST] [VIDEO_PLAYER] Starting video playback process");
-    println!("[RUST] [VIDEO_PLAYER] Stream URL received from JavaScript: {}", stream_url);
-    println!("[RUST] [VIDEO_PLAYER] Stream type: {}", if stream_url.starts_with("magnet:") { "Magnet Link" } else { "Direct URL" });
-    println!("[RUST] [VIDEO_PLAYER] URL length: {} characters", stream_url.len());
-    println!("[RUST] [VIDEO_PLAYER] First 100 chars of URL: {}", &stream_url.chars().take(100).collect::<String>());
-
-    let shell = app.shell();
-
-    // Check if it's a magnet link
-    if stream_url.starts_with("magnet:") {
-        println!("[RUST] [VIDEO_PLAYER] ================================================");
-        println!("[RUST] [VIDEO_PLAYER] Magnet link detected - starting Peerflix streaming");
-        println!("[RUST] [VIDEO_PLAYER] ================================================");
+    imdb_id: String,
+    season: Option<u32>,
+    episode: Option<u32>,
+    state: State<'_, AppState>
+) -> Result<StreamsComplete, DeckflixError> {
+    println!("[RUST] [FETCH_STREAMS_COMMAND] Progressive stream fetch for {} (season: {:?}, episode: {:?})", imdb_id, season, episode);
+
+    let complete = state.client.fetch_streams_progressive(&imdb_id, season, episode, |progress| {
+        if let Err(e) = app.emit("streams-progress", &progress) {
+            println!("[RUST] [FETCH_STREAMS_COMMAND] ⚠️  Failed to emit streams-progress: {}", e);
+        }
+    }).await?;
 
-        // Start Peerflix streaming
-        let streamer = state.streamer.lock().await;
-        let torrent_dir = streamer.start_stream(stream_url).await?;
+    if let Err(e) = app.emit("streams-complete", &complete) {
+        println!("[RUST] [FETCH_STREAMS_COMMAND] ⚠️  Failed to emit streams-complete: {}", e);
+    }
 
-        println!("[RUST] [VIDEO_PLAYER] 🎯 Torrent directory: {}", torrent_dir);
-        println!("[RUST] [VIDEO_PLAYER] 🔍 Waiting for video file to appear in directory...");
+    Ok(complete)
+}
 
-        // Find the largest video file in the torrent directory
-        use std::fs;
-        use std::path::Path;
-        use std::time::Duration;
+// Extra player arguments for a direct stream from its behaviorHints.
+// mpv can send any request header, VLC only the user agent and referrer.
+fn player_stream_args(player: &str, hints: Option<&StreamBehaviorHints>) -> Vec<String> {
+    let hints = match hints {
+        Some(hints) => hints,
+        None => return Vec::new(),
+    };
 
-        let path = Path::new(&torrent_dir);
+    let player = player.to_lowercase();
+    let mut args = Vec::new();
 
-        // Wait for directory to be created (max 30 seconds)
-        let mut attempts = 0;
-        let max_attempts = 60; // 60 attempts * 500ms = 30 seconds
-        while !path.exists() && attempts < max_attempts {
-            println!("[RUST] [VIDEO_PLAYER] ⏳ Waiting for torrent directory to be created... (attempt {}/{})", attempts + 1, max_attempts);
-            tokio::time::sleep(Duration::from_millis(500)).await;
-            attempts += 1;
+    if player.contains("mpv") {
+        for (name, value) in hints.request_headers() {
+            match name.to_lowercase().as_str() {
+                "user-agent" => args.push(format!("--user-agent={}", value)),
+                "referer" => args.push(format!("--referrer={}", value)),
+                _ => args.push(format!("--http-header-fields-append={}: {}", name, value)),
+            }
         }
-
-        if !path.exists() {
-            return Err(format!("Torrent directory was not created after {} seconds: {}", max_attempts / 2, torrent_dir));
+        if let Some(filename) = &hints.filename {
+            args.push(format!("--force-media-title={}", filename));
         }
-
-        println!("[RUST] [VIDEO_PLAYER] ✅ Torrent directory exists");
-
-        let video_extensions = vec!["mp4", "mkv", "avi", "mov", "wmv", "flv", "webm", "m4v"];
-        let mut video_files: Vec<(String, u64)> = Vec::new();
-
-        // Recursively search for video files
-        fn find_videos(dir: &Path, extensions: &[&str], files: &mut Vec<(String, u64)>) -> std::io::Result<()> {
-            if dir.is_dir() {
-                for entry in fs::read_dir(dir)? {
-                    let entry = entry?;
-                    let path = entry.path();
-                    if path.is_dir() {
-                        find_videos(&path, extensions, files)?;
-                    } else if let Some(ext) = path.extension() {
-                        if extensions.contains(&ext.to_str().unwrap_or("").to_lowercase().as_str()) {
-                            if let Ok(metadata) = fs::metadata(&path) {
-                                files.push((path.to_string_lossy().to_string(), metadata.len()));
-                                println!("[RUST] [VIDEO_PLAYER] Found video: {} ({} bytes)", path.display(), metadata.len());
-                            }
-                        }
-                    }
-                }
+    } else if player.contains("vlc") {
+        for (name, value) in hints.request_headers() {
+            match name.to_lowercase().as_str() {
+                "user-agent" => args.push(format!("--http-user-agent={}", value)),
+                "referer" => args.push(format!("--http-referrer={}", value)),
+                _ => println!("[RUST] [VIDEO_PLAYER] ⚠️  VLC can't send header '{}', playback may fail", name),
             }
-            Ok(())
         }
+    }
 
-        // Wait for video files to appear (max 60 seconds)
-        let mut video_attempts = 0;
-        let max_video_attempts = 120; // 120 attempts * 500ms = 60 seconds
-
-        while video_files.is_empty() && video_attempts < max_video_attempts {
-            video_files.clear();
-            if let Err(e) = find_videos(path, &video_extensions, &mut video_files) {
-                println!("[RUST] [VIDEO_PLAYER] ⚠️  Error searching for videos: {}", e);
-            }
-
-            if video_files.is_empty() {
-                println!("[RUST] [VIDEO_PLAYER] ⏳ Waiting for video files to appear... (attempt {}/{}, found {} files)",
-                    video_attempts + 1, max_video_attempts, video_files.len());
-                tokio::time::sleep(Duration::from_millis(500)).await;
-                video_attempts += 1;
-            }
-        }
+    args
+}
 
-        if video_files.is_empty() {
-            return Err(format!("No video files found in torrent directory after {} seconds: {}", max_video_attempts / 2, torrent_dir));
+#[tauri::command]
+async fn play_video_external(
+    app: tauri::AppHandle,
+    state: State<'_, AppState>,
+    mut stream: Stream,
+    episode: Option<EpisodeRef>,
+    file_index: Option<u32>,
+) -> Result<String, DeckflixError> {
+    // A file picked from the torrent's file list is played as is
+    let episode = match file_index {
+        Some(index) => {
+            stream.file_idx = Some(index);
+            None
         }
+        None => episode,
+    };
 
-        println!("[RUST] [VIDEO_PLAYER] ✅ Found {} video file(s)", video_files.len());
-
-        // Wait for the video file to have some data downloaded (at least 10 MB)
-        let min_file_size = 10 * 1024 * 1024; // 10 MB
-        let mut size_attempts = 0;
-        let max_size_attempts = 60; // 60 attempts * 500ms = 30 seconds
+    // A downloaded copy is played from disk instead
+    let local_file = state.downloads.local_file(&stream, episode);
+    let (stream_url, behavior_hints) = match (&local_file, &stream.url, &stream.info_hash) {
+        (Some(path), _, _) => (path.to_string_lossy().into_owned(), None),
+        (None, Some(url), _) => (url.clone(), stream.behavior_hints.clone()),
+        // The engine's stream server provides the URL once the torrent has started
+        (None, None, Some(info_hash)) => (format!("torrent {}", info_hash), None),
+        (None, None, None) => return Err(DeckflixError::invalid_request("Stream has neither a URL nor an infoHash")),
+    };
 
-        // Sort by size (largest first) and take the largest one
-        video_files.sort_by(|a, b| b.1.cmp(&a.1));
-        let video_path = video_files[0].0.clone();
+    println!("[RUST] [VIDEO_PLAYER] ==============================================");
+    println!("[RUST] [VIDEO_PLAYER] Starting video playback process");
+    println!("[RUST] [VIDEO_PLAYER] Stream URL received from JavaScript: {}", stream_url);
+    println!("[RUST] [VIDEO_PLAYER] Stream type: {}", if local_file.is_some() { "Downloaded file" } else if stream.info_hash.is_some() { "Torrent" } else { "Direct URL" });
+    println!("[RUST] [VIDEO_PLAYER] URL length: {} characters", stream_url.len());
+    println!("[RUST] [VIDEO_PLAYER] First 100 chars of URL: {}", &stream_url.chars().take(100).collect::<String>());
 
-        println!("[RUST] [VIDEO_PLAYER] 📦 Selected video: {}", video_path);
-        println!("[RUST] [VIDEO_PLAYER] ⏳ Waiting for video file to have sufficient data downloaded...");
+    let shell = app.shell();
 
-        while size_attempts < max_size_attempts {
-            if let Ok(metadata) = fs::metadata(&video_path) {
-                let current_size = metadata.len();
-                println!("[RUST] [VIDEO_PLAYER] 📊 Current file size: {:.2} MB", current_size as f64 / 1024.0 / 1024.0);
+    // Torrents are streamed by the built-in engine from their infoHash and file index
+    if let (Some(info_hash), None) = (&stream.info_hash, &local_file) {
+        println!("[RUST] [VIDEO_PLAYER] ================================================");
+        println!("[RUST] [VIDEO_PLAYER] Torrent stream detected - starting torrent engine");
+        println!("[RUST] [VIDEO_PLAYER] ================================================");
 
-                if current_size >= min_file_size {
-                    println!("[RUST] [VIDEO_PLAYER] ✅ Video file has sufficient data ({:.2} MB >= 10 MB)", current_size as f64 / 1024.0 / 1024.0);
-                    break;
-                }
+        // Startup states go to the UI as "torrent-startup"; this returns once the
+        // start of the file is buffered or startup failed. While the stream runs,
+        // its statistics follow as "stream-stats".
+        let stats_app = app.clone();
+        let started = state.streamer.start_stream(info_hash, stream.file_idx, episode, &stream.sources, |startup| {
+            if let Err(e) = app.emit("torrent-startup", &startup) {
+                println!("[RUST] [VIDEO_PLAYER] ⚠️  Failed to emit torrent-startup: {}", e);
             }
+        }, move |stats| {
+            if let Err(e) = stats_app.emit("stream-stats", &stats) {
+                println!("[RUST] [VIDEO_PLAYER] ⚠️  Failed to emit stream-stats: {}", e);
+            }
+        }).await?;
+        let torrent_url = started.url;
 
-            tokio::time::sleep(Duration::from_millis(500)).await;
-            size_attempts += 1;
-        }
-
-        println!("[RUST] [VIDEO_PLAYER] 🎬 Ready to launch MPV with video file");
-
-        println!("[RUST] [VIDEO_PLAYER] 🎬 Selected video file: {}", video_path);
-        println!("[RUST] [VIDEO_PLAYER] 📺 Launching MPV player with video file");
+        println!("[RUST] [VIDEO_PLAYER] 🎬 Ready to launch MPV with torrent stream");
+        println!("[RUST] [VIDEO_PLAYER] 📺 Launching MPV player with: {}", torrent_url);
 
-        // Launch MPV with the video file path - cross-platform support
+        // Launch MPV with the local stream URL - cross-platform support
         let mpv_players: Vec<&str> = if cfg!(target_os = "windows") {
             vec![
                 "mpv",
@@ -195,19 +208,21 @@ async fn play_video_external(
                     println!("[RUST] [VIDEO_PLAYER] Launching Flatpak app: {}", flatpak_app);
                     shell.command("flatpak")
                         .args(&["run", flatpak_app])
-                        .arg(&video_path)
+                        .arg(&torrent_url)
                         .spawn()
                 } else {
                     continue;
                 }
             } else {
-                shell.command(player).arg(&video_path).spawn()
+                shell.command(player).arg(&torrent_url).spawn()
             };
 
             match result {
                 Ok((mut rx, mut child)) => {
-                    let success_msg = format!("Successfully launched MPV with video file (PID: {:?})", child.pid());
+                    let success_msg = format!("Successfully launched MPV with torrent stream (PID: {:?})", child.pid());
                     println!("[RUST] [VIDEO_PLAYER] ✅ {}", success_msg);
+                    // The player is stopped with the stream session
+                    state.streamer.attach_player(started.id, child.pid()).await;
                     return Ok(success_msg);
                 }
                 Err(e) => {
@@ -217,14 +232,14 @@ async fn play_video_external(
             }
         }
 
-        return Err("MPV player not found. Please install MPV from mpv.io or via your package manager".to_string());
+        return Err(DeckflixError::player_launch("MPV player not found. Please install MPV from mpv.io or via your package manager"));
     }
 
     // Direct HTTP URL - cross-platform video player support
     println!("[RUST] [VIDEO_PLAYER] Processing direct URL stream...");
 
     // Try different video players in order of preference - cross-platform
-    let players: Vec<&str> = if cfg!(target_os = "windows") {
+    let mut players: Vec<&str> = if cfg!(target_os = "windows") {
         vec![
             "vlc",                                          // System VLC
             "C:\\Program Files\\VideoLAN\\VLC\\vlc.exe",    // Windows VLC 64-bit
@@ -245,6 +260,14 @@ async fn play_video_external(
         ]
     };
 
+    // Header-protected streams need a player that can send the headers: try mpv first
+    let request_headers = behavior_hints.as_ref().map(|h| h.request_headers()).unwrap_or_default();
+    if !request_headers.is_empty() {
+        println!("[RUST] [VIDEO_PLAYER] Stream requires {} request header(s), preferring mpv", request_headers.len());
+        players.retain(|p| *p != "xdg-open");
+        players.sort_by_key(|p| !p.to_lowercase().contains("mpv"));
+    }
+
     println!("[RUST] [VIDEO_PLAYER] Available video players to try: {:?}", players);
     println!("[RUST] [VIDEO_PLAYER] Starting player detection and launch sequence...");
     
@@ -252,6 +275,8 @@ async fn play_video_external(
         println!("[RUST] [VIDEO_PLAYER] ----------------------------------------------");
         println!("[RUST] [VIDEO_PLAYER] Attempting player {}/{}: {}", index + 1, players.len(), player);
 
+        let stream_args = player_stream_args(player, behavior_hints.as_ref());
+
         let result = if player.contains("flatpak run") {
             // Handle Flatpak commands specially
             let parts: Vec<&str> = player.split_whitespace().collect();
@@ -264,6 +289,7 @@ async fn play_video_external(
 
                 shell.command("flatpak")
                     .args(&["run", flatpak_app])
+                    .args(&stream_args)
                     .arg(&stream_url)
                     .spawn()
             } else {
@@ -273,8 +299,8 @@ async fn play_video_external(
         } else {
             // Regular command
             println!("[RUST] [VIDEO_PLAYER] Regular command launch");
-            println!("[RUST] [VIDEO_PLAYER] Command: {} \"{}\"", player, stream_url);
-            shell.command(player).arg(&stream_url).spawn()
+            println!("[RUST] [VIDEO_PLAYER] Command: {} {:?} \"{}\"", player, stream_args, stream_url);
+            shell.command(player).args(&stream_args).arg(&stream_url).spawn()
         };
 
         match result {
@@ -298,79 +324,227 @@ async fn play_video_external(
     println!("[RUST] [VIDEO_PLAYER] Tried {} different players", players.len());
     println!("[RUST] [VIDEO_PLAYER] ==============================================");
 
-    Err("No video player found. Please install:\n• Windows: Download MPV from mpv.io or VLC from videolan.org\n• Steam Deck: Run 'sudo pacman -S mpv' or install via Discover app".to_string())
+    Err(DeckflixError::player_launch("No video player found. Please install:\n• Windows: Download MPV from mpv.io or VLC from videolan.org\n• Steam Deck: Run 'sudo pacman -S mpv' or install via Discover app"))
 }
 
 #[tauri::command]
-async fn fetch_popular_series(state: State<'_, AppState>) -> Result<Vec<Series>, String> {
-    let client = state.client.lock().await;
-    client.fetch_popular_series().await
+async fn fetch_popular_series(extra: Option<CatalogExtra>, state: State<'_, AppState>) -> Result<Vec<Series>, DeckflixError> {
+    state.client.fetch_popular_series(&extra.unwrap_or_default()).await
 }
 
 #[tauri::command]
-async fn fetch_popular_anime(state: State<'_, AppState>) -> Result<Vec<Anime>, String> {
-    let client = state.client.lock().await;
-    client.fetch_popular_anime().await
+async fn fetch_home_rows(state: State<'_, AppState>) -> Result<Vec<HomeRow>, DeckflixError> {
+    state.client.fetch_home_rows().await
 }
 
+// Every available row with its hidden flag, for the home screen settings
 #[tauri::command]
-async fn search_content(query: String, state: State<'_, AppState>) -> Result<Vec<SearchResult>, String> {
-    let client = state.client.lock().await;
-    client.search_content(&query).await
+async fn list_home_rows(state: State<'_, AppState>) -> Result<Vec<HomeRow>, DeckflixError> {
+    Ok(state.client.list_home_rows())
 }
 
 #[tauri::command]
-async fn get_addon_status() -> Result<String, String> {
+async fn set_home_rows(settings: Vec<HomeRowSetting>, state: State<'_, AppState>) -> Result<Vec<HomeRow>, DeckflixError> {
+    state.client.set_home_rows(settings)
+}
+
+// Trackers added to torrent streams that come without any
+#[tauri::command]
+async fn get_fallback_trackers(state: State<'_, AppState>) -> Result<Vec<String>, DeckflixError> {
+    Ok(state.client.fallback_trackers())
+}
+
+#[tauri::command]
+async fn set_fallback_trackers(trackers: Vec<String>, state: State<'_, AppState>) -> Result<Vec<String>, DeckflixError> {
+    state.client.set_fallback_trackers(trackers)
+}
+
+#[tauri::command]
+async fn fetch_catalog_genres(content_type: String, state: State<'_, AppState>) -> Result<Vec<String>, DeckflixError> {
+    Ok(state.client.catalog_genres(&content_type, "top"))
+}
+
+#[tauri::command]
+async fn fetch_popular_anime(state: State<'_, AppState>) -> Result<Vec<Anime>, DeckflixError> {
+    state.client.fetch_popular_anime().await
+}
+
+#[tauri::command]
+async fn search_content(query: String, state: State<'_, AppState>) -> Result<Vec<SearchResult>, DeckflixError> {
+    state.client.search_content(&query).await
+}
+
+#[tauri::command]
+async fn fetch_meta(content_type: String, id: String, state: State<'_, AppState>) -> Result<MetaDetails, DeckflixError> {
+    state.client.fetch_meta(&content_type, &id).await
+}
+
+#[tauri::command]
+async fn get_addon_status() -> Result<String, DeckflixError> {
     Ok("Ready".to_string())
 }
 
 #[tauri::command]
-async fn stop_video_stream(state: State<'_, AppState>) -> Result<(), String> {
-    let streamer = state.streamer.lock().await;
-    streamer.stop_stream().await
+async fn list_addons(state: State<'_, AppState>) -> Result<Vec<Addon>, DeckflixError> {
+    Ok(state.client.list_addons())
 }
 
-fn main() {
-    // Initialize the addon client and torrent streamer
-    let client = AddonClient::new();
-    let streamer = TorrentStreamer::new();
-    let app_state = AppState {
-        client: Mutex::new(client),
-        streamer: Arc::new(Mutex::new(streamer)),
-    };
+#[tauri::command]
+async fn install_addon(manifest_url: String, state: State<'_, AppState>) -> Result<Addon, DeckflixError> {
+    state.client.install_addon(&manifest_url).await
+}
+
+#[tauri::command]
+async fn remove_addon(addon_id: String, state: State<'_, AppState>) -> Result<(), DeckflixError> {
+    state.client.remove_addon(&addon_id)
+}
+
+#[tauri::command]
+async fn reorder_addons(addon_ids: Vec<String>, state: State<'_, AppState>) -> Result<Vec<Addon>, DeckflixError> {
+    state.client.reorder_addons(&addon_ids)
+}
+
+#[tauri::command]
+async fn list_ranking_profiles(state: State<'_, AppState>) -> Result<RankingSettings, DeckflixError> {
+    Ok(state.client.list_ranking_profiles())
+}
+
+#[tauri::command]
+async fn save_ranking_profile(profile: RankingProfile, state: State<'_, AppState>) -> Result<RankingSettings, DeckflixError> {
+    state.client.save_ranking_profile(profile)
+}
+
+#[tauri::command]
+async fn remove_ranking_profile(profile_id: String, state: State<'_, AppState>) -> Result<RankingSettings, DeckflixError> {
+    state.client.remove_ranking_profile(&profile_id)
+}
+
+#[tauri::command]
+async fn set_active_ranking_profile(profile_id: String, state: State<'_, AppState>) -> Result<RankingSettings, DeckflixError> {
+    state.client.set_active_ranking_profile(&profile_id)
+}
+
+#[tauri::command]
+async fn clear_cache(state: State<'_, AppState>) -> Result<(), DeckflixError> {
+    state.client.clear_cache()
+}
+
+#[tauri::command]
+async fn stop_video_stream(state: State<'_, AppState>) -> Result<(), DeckflixError> {
+    state.streamer.stop_stream().await
+}
+
+// Statistics of the torrent being streamed, or None when nothing is streaming
+#[tauri::command]
+async fn get_stream_stats(state: State<'_, AppState>) -> Result<Option<StreamStats>, DeckflixError> {
+    Ok(state.streamer.stats().await)
+}
 
+// The files of a torrent stream and the one that would play for `episode`, so the user can pick another
+#[tauri::command]
+async fn list_torrent_files(stream: Stream, episode: Option<EpisodeRef>, state: State<'_, AppState>) -> Result<TorrentFileList, DeckflixError> {
+    let info_hash = stream
+        .info_hash
+        .as_deref()
+        .ok_or_else(|| DeckflixError::invalid_request("Only torrent streams have a file list"))?;
+    state.streamer.list_files(info_hash, stream.file_idx, episode, &stream.sources).await
+}
+
+#[tauri::command]
+async fn get_torrent_cache(state: State<'_, AppState>) -> Result<TorrentCacheStatus, DeckflixError> {
+    state.torrent_cache.status().await
+}
+
+#[tauri::command]
+async fn set_torrent_cache_settings(settings: TorrentCacheSettings, state: State<'_, AppState>) -> Result<TorrentCacheStatus, DeckflixError> {
+    state.torrent_cache.set_settings(settings).await
+}
+
+// Removes one torrent's data, or all of it when no info hash is given; torrents in use are kept
+#[tauri::command]
+async fn clear_torrent_cache(info_hash: Option<String>, state: State<'_, AppState>) -> Result<TorrentCacheStatus, DeckflixError> {
+    state.torrent_cache.clear(info_hash).await
+}
+
+#[tauri::command]
+async fn list_downloads(state: State<'_, AppState>) -> Result<Vec<Download>, DeckflixError> {
+    Ok(state.downloads.list())
+}
+
+// Starts downloading a stream for offline playback; progress follows as "download-progress"
+#[tauri::command]
+async fn queue_download(
+    stream: Stream,
+    title: Option<String>,
+    episode: Option<EpisodeRef>,
+    state: State<'_, AppState>,
+) -> Result<Download, DeckflixError> {
+    state.downloads.queue(stream, title, episode)
+}
+
+#[tauri::command]
+async fn pause_download(id: String, state: State<'_, AppState>) -> Result<Download, DeckflixError> {
+    state.downloads.pause(&id).await
+}
+
+#[tauri::command]
+async fn resume_download(id: String, state: State<'_, AppState>) -> Result<Download, DeckflixError> {
+    state.downloads.resume(&id)
+}
+
+#[tauri::command]
+async fn remove_download(id: String, delete_file: bool, state: State<'_, AppState>) -> Result<(), DeckflixError> {
+    state.downloads.remove(&id, delete_file).await
+}
+
+fn main() {
     tauri::Builder::default()
         .plugin(tauri_plugin_shell::init())
         .setup(|app| {
+            // Initialize the addon client, torrent streamer and download manager
+            // Installed addons, settings and offline downloads are stored in the app
+            // data directory, cached addon responses and torrent downloads in the app
+            // cache directory unless the torrent cache was moved elsewhere. The torrent
+            // engine keeps its own state next to, not inside, the torrent cache
+            let data_dir = app.path().app_data_dir()?;
+            let cache_dir = app.path().app_cache_dir()?;
+            let torrent_cache = Arc::new(TorrentCache::new(&data_dir, cache_dir.join("torrents")));
+            let streamer = TorrentStreamer::new(cache_dir.join("engine"), torrent_cache.clone());
+            let handle = app.handle().clone();
+            let downloads = Arc::new(DownloadManager::new(&data_dir, data_dir.join("downloads"), cache_dir.join("downloads"), move |download| {
+                if let Err(e) = handle.emit("download-progress", download) {
+                    println!("[RUST] [DOWNLOADS] ⚠️  Failed to emit download-progress: {}", e);
+                }
+            }));
+            tauri::async_runtime::spawn({
+                let downloads = downloads.clone();
+                async move { downloads.resume_interrupted() }
+            });
+            let client = AddonClient::new(data_dir, cache_dir.join("http"));
+            let app_state = AppState {
+                client,
+                streamer: Arc::new(streamer),
+                torrent_cache,
+                downloads,
+            };
+
             app.manage(app_state);
             Ok(())
         })
         .on_window_event(|window, event| {
             match event {
                 tauri::WindowEvent::Destroyed => {
-                    println!("[RUST] [CLEANUP] Application window destroyed - cleaning up Peerflix processes");
-
-                    // Kill any remaining peerflix processes
-                    #[cfg(target_os = "windows")]
-                    {
-                        let _ = std::process::Command::new("taskkill")
-                            .args(&["/f", "/im", "peerflix.cmd"])
-                            .output();
-                        let _ = std::process::Command::new("taskkill")
-                            .args(&["/f", "/im", "node.exe"])
-                            .arg("/fi")
-                            .arg("WINDOWTITLE eq peerflix*")
-                            .output();
-                    }
-
-                    #[cfg(not(target_os = "windows"))]
-                    {
-                        let _ = std::process::Command::new("pkill")
-                            .arg("peerflix")
-                            .output();
+                    println!("[RUST] [CLEANUP] Application window destroyed - stopping torrent stream");
+
+                    let state = window.state::<AppState>();
+                    let result = tauri::async_runtime::block_on(async {
+                        state.streamer.stop_stream().await
+                    });
+                    if let Err(e) = result {
+                        println!("[RUST] [CLEANUP] ⚠️  Failed to stop torrent stream: {}", e);
                     }
 
-                    println!("[RUST] [CLEANUP] Peerflix cleanup completed");
+                    println!("[RUST] [CLEANUP] Torrent cleanup completed");
                 }
                 _ => {}
             }
@@ -379,11 +553,38 @@ fn main() {
             fetch_popular_movies,
             fetch_popular_series,
             fetch_popular_anime,
+            fetch_catalog_genres,
+            fetch_home_rows,
+            list_home_rows,
+            set_home_rows,
+            get_fallback_trackers,
+            set_fallback_trackers,
             search_content,
+            fetch_meta,
             fetch_streams,
+            fetch_streams_progressive,
             play_video_external,
             stop_video_stream,
-            get_addon_status
+            get_stream_stats,
+            list_torrent_files,
+            get_torrent_cache,
+            set_torrent_cache_settings,
+            clear_torrent_cache,
+            list_downloads,
+            queue_download,
+            pause_download,
+            resume_download,
+            remove_download,
+            get_addon_status,
+            list_addons,
+            install_addon,
+            remove_addon,
+            reorder_addons,
+            list_ranking_profiles,
+            save_ranking_profile,
+            remove_ranking_profile,
+            set_active_ranking_profile,
+            clear_cache
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
@@ -1,4 +1,8 @@
-use serde::{Deserialize, Serialize};
+use crate::error::DeckflixError;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Deserializer, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Movie {
@@ -17,6 +21,14 @@ pub struct Movie {
     pub runtime: Option<String>,
     pub country: Option<String>,
     pub language: Option<String>,
+    pub release_info: Option<String>, // "2010" or "2011-2019"
+    pub released: Option<String>,
+    pub logo: Option<String>,
+    #[serde(default)]
+    pub trailers: Vec<MetaTrailer>,
+    #[serde(default)]
+    pub links: Vec<MetaLink>,
+    pub behavior_hints: Option<MetaBehaviorHints>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -36,6 +48,14 @@ pub struct Series {
     pub runtime: Option<String>,
     pub country: Option<String>,
     pub language: Option<String>,
+    pub release_info: Option<String>, // "2010" or "2011-2019"
+    pub released: Option<String>,
+    pub logo: Option<String>,
+    #[serde(default)]
+    pub trailers: Vec<MetaTrailer>,
+    #[serde(default)]
+    pub links: Vec<MetaLink>,
+    pub behavior_hints: Option<MetaBehaviorHints>,
     // Series-specific fields
     pub seasons: Option<u32>,
     pub episodes: Option<u32>,
@@ -43,6 +63,30 @@ pub struct Series {
     pub network: Option<String>,
 }
 
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Episode {
+    pub id: String, // Stremio video ID, e.g. "tt0944947:1:1"
+    pub title: String,
+    pub season: u32,
+    pub episode: u32,
+    pub released: Option<String>, // ISO timestamp of the air date
+    pub thumbnail: Option<String>,
+    pub overview: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Season {
+    pub number: u32, // 0 holds specials
+    pub episodes: Vec<Episode>,
+}
+
+// Full meta for a single title, as returned by the meta resource
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct MetaDetails {
+    pub series: Series,
+    pub seasons: Vec<Season>,
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Anime {
     pub id: String,
@@ -60,6 +104,14 @@ pub struct Anime {
     pub runtime: Option<String>,
     pub country: Option<String>,
     pub language: Option<String>,
+    pub release_info: Option<String>, // "2010" or "2011-2019"
+    pub released: Option<String>,
+    pub logo: Option<String>,
+    #[serde(default)]
+    pub trailers: Vec<MetaTrailer>,
+    #[serde(default)]
+    pub links: Vec<MetaLink>,
+    pub behavior_hints: Option<MetaBehaviorHints>,
     // Anime-specific fields
     pub seasons: Option<u32>,
     pub episodes: Option<u32>,
@@ -73,21 +125,165 @@ pub struct Anime {
 pub struct Stream {
     pub name: Option<String>,
     pub title: String,
-    pub url: String,
+    #[serde(default)]
+    pub url: Option<String>, // direct URL; None for torrents, which play by info_hash
+    #[serde(default)]
+    pub info_hash: Option<String>, // lowercase hex
+    #[serde(default)]
+    pub file_idx: Option<u32>, // 0-based file index inside the torrent
+    #[serde(default)]
+    pub sources: Vec<String>, // "tracker:<url>" and "dht:<info hash>" peer sources
     pub behavior_hints: Option<StreamBehaviorHints>,
     pub quality: Option<String>,
     pub size: Option<String>,
     pub seeders: Option<u32>,
     pub leechers: Option<u32>,
     pub source: Option<String>, // torrent, direct, etc.
+    #[serde(default)]
+    pub release: ReleaseInfo, // parsed from the title, for badges and ranking
+    #[serde(default)]
+    pub addons: Vec<String>, // names of every addon that returned this stream
     pub language: Option<String>,
     pub subtitles: Option<Vec<String>>,
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+// What a release name says about the file, e.g. "Movie.2019.2160p.WEB-DL.DDP5.1.DV.HEVC-GROUP"
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct ReleaseInfo {
+    pub resolution: Option<String>, // "2160p", "1080p", ...
+    pub source: Option<String>, // "REMUX", "BluRay", "WEB-DL", "CAM", ...
+    pub video_codec: Option<String>, // "HEVC", "AVC", "AV1", ...
+    pub bit_depth: Option<u8>,
+    pub hdr: Vec<String>, // "DV", "HDR10+", "HDR10", "HDR", "HLG"
+    pub audio_codec: Option<String>, // "TrueHD", "DTS-HD MA", "DD+", "AAC", ...
+    pub audio_channels: Option<String>, // "5.1", "7.1", ...
+    pub atmos: bool,
+    pub languages: Vec<String>,
+    pub release_group: Option<String>,
+    pub season: Option<u32>,
+    pub episode: Option<u32>,
+}
+
+impl ReleaseInfo {
+    // Camera recordings and their kin, whatever resolution they claim
+    pub fn is_low_quality_source(&self) -> bool {
+        matches!(self.source.as_deref(), Some("CAM" | "TS" | "TC" | "SCR"))
+    }
+
+    // Short label such as "1080p BluRay" or "HEVC" when nothing better is known
+    pub fn quality_label(&self) -> Option<String> {
+        let parts: Vec<&str> = [self.resolution.as_deref(), self.source.as_deref()]
+            .into_iter()
+            .flatten()
+            .collect();
+
+        if parts.is_empty() {
+            self.video_codec.clone()
+        } else {
+            Some(parts.join(" "))
+        }
+    }
+}
+
+// Stremio stream behaviorHints, kept in their wire (camelCase) form so the
+// frontend can hand them back to play_video_external unchanged
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(rename_all = "camelCase")]
 pub struct StreamBehaviorHints {
+    // The URL can't be played by a browser (e.g. needs headers or is MKV/HEVC)
+    #[serde(default)]
     pub not_web_ready: Option<bool>,
-    pub proxy_headers: Option<serde_json::Value>,
+    // Streams sharing a binge group can be auto-picked for the next episode
+    #[serde(default)]
+    pub binge_group: Option<String>,
+    #[serde(default)]
+    pub country_whitelist: Option<Vec<String>>,
+    #[serde(default)]
+    pub proxy_headers: Option<StreamProxyHeaders>,
+    #[serde(default)]
+    pub filename: Option<String>,
+    #[serde(default)]
+    pub video_size: Option<u64>,
+    // OpenSubtitles hash of the video file
+    #[serde(default)]
+    pub video_hash: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct StreamProxyHeaders {
+    // Headers the player must send when requesting the stream URL
+    #[serde(default)]
+    pub request: BTreeMap<String, String>,
+    #[serde(default)]
+    pub response: BTreeMap<String, String>,
+}
+
+impl StreamBehaviorHints {
+    pub fn request_headers(&self) -> Vec<(&str, &str)> {
+        self.proxy_headers
+            .as_ref()
+            .map(|h| h.request.iter().map(|(k, v)| (k.as_str(), v.as_str())).collect())
+            .unwrap_or_default()
+    }
+}
+
+// Saved preferences used to rank streams, e.g. "1080p at most 4 GB, no HEVC"
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct RankingProfile {
+    pub id: String,
+    pub name: String,
+    #[serde(default)]
+    pub preferred_resolution: Option<String>, // "1080p"
+    #[serde(default)]
+    pub max_resolution: Option<String>,
+    #[serde(default)]
+    pub min_size_gb: Option<f64>,
+    #[serde(default)]
+    pub max_size_gb: Option<f64>,
+    #[serde(default)]
+    pub preferred_codecs: Vec<String>, // ReleaseInfo codec names, e.g. "AVC"
+    #[serde(default)]
+    pub avoided_codecs: Vec<String>,
+    #[serde(default)]
+    pub preferred_languages: Vec<String>, // e.g. "English", "French"
+    #[serde(default)]
+    pub blocked_terms: Vec<String>, // case-insensitive, matched against name and title
+    #[serde(default)]
+    pub min_seeders: Option<u32>,
+}
+
+// Every saved profile and which one ranks streams
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct RankingSettings {
+    pub active: String,
+    pub profiles: Vec<RankingProfile>,
+}
+
+// Outcome of asking one addon for streams
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct StreamSourceStatus {
+    pub addon_id: String,
+    pub addon_name: String,
+    pub success: bool,
+    pub stream_count: usize,
+    pub error: Option<DeckflixError>,
+}
+
+// Emitted as "streams-progress" each time an addon answers
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct StreamsProgress {
+    pub video_id: String,
+    pub source: StreamSourceStatus,
+    pub streams: Vec<Stream>, // streams from this addon only
+    pub ranking: Vec<Stream>, // every stream received so far, best first
+}
+
+// Emitted as "streams-complete" once every addon has answered or timed out
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct StreamsComplete {
+    pub video_id: String,
+    pub sources: Vec<StreamSourceStatus>,
+    pub total_streams: usize,
 }
 
 // Content trait for shared functionality
@@ -143,6 +339,332 @@ pub struct SearchResult {
     pub description: Option<String>,
 }
 
+// A Stremio meta object as sent by catalog and meta resources. Addons disagree
+// on whether numbers are strings, so those fields accept either. Movie, Series,
+// Anime and SearchResult are built from it.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct MetaItem {
+    pub id: String,
+    #[serde(rename = "type", default)]
+    pub content_type: String,
+    pub name: String,
+    #[serde(default)]
+    pub poster: Option<String>,
+    #[serde(default)]
+    pub poster_shape: Option<String>,
+    #[serde(default)]
+    pub background: Option<String>,
+    #[serde(default)]
+    pub logo: Option<String>,
+    #[serde(default)]
+    pub description: Option<String>,
+    #[serde(default, deserialize_with = "lenient_string")]
+    pub release_info: Option<String>,
+    #[serde(default, deserialize_with = "lenient_string")]
+    pub year: Option<String>,
+    #[serde(default)]
+    pub released: Option<String>,
+    #[serde(default, deserialize_with = "lenient_string")]
+    pub imdb_rating: Option<String>,
+    #[serde(default, deserialize_with = "lenient_string")]
+    pub runtime: Option<String>,
+    #[serde(default)]
+    pub country: Option<String>,
+    #[serde(default)]
+    pub language: Option<String>,
+    #[serde(default)]
+    pub website: Option<String>,
+    #[serde(default)]
+    pub awards: Option<String>,
+    // Cinemeta sends both "genre" and "genres"
+    #[serde(default, deserialize_with = "lenient_list")]
+    pub genre: Option<Vec<String>>,
+    #[serde(default, deserialize_with = "lenient_list")]
+    pub genres: Option<Vec<String>>,
+    #[serde(default, deserialize_with = "lenient_list")]
+    pub director: Option<Vec<String>>,
+    #[serde(default, deserialize_with = "lenient_list")]
+    pub cast: Option<Vec<String>>,
+    #[serde(default, deserialize_with = "lenient_list")]
+    pub writer: Option<Vec<String>>,
+    #[serde(default, deserialize_with = "lenient_entries")]
+    pub trailers: Vec<MetaTrailer>,
+    #[serde(default, deserialize_with = "lenient_entries")]
+    pub links: Vec<MetaLink>,
+    #[serde(default, deserialize_with = "lenient_entries")]
+    pub videos: Vec<MetaVideo>,
+    #[serde(default)]
+    pub behavior_hints: Option<MetaBehaviorHints>,
+    // Not in the Stremio schema, but sent by some series and anime addons
+    #[serde(default, deserialize_with = "lenient_u32")]
+    pub seasons: Option<u32>,
+    #[serde(default, deserialize_with = "lenient_u32")]
+    pub episodes: Option<u32>,
+    #[serde(default)]
+    pub status: Option<String>,
+    #[serde(default)]
+    pub network: Option<String>,
+    #[serde(default)]
+    pub studio: Option<String>,
+    #[serde(default, deserialize_with = "lenient_string")]
+    pub mal_rating: Option<String>,
+    #[serde(default)]
+    pub anime_type: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct MetaTrailer {
+    pub source: String, // YouTube video ID
+    #[serde(rename = "type", default)]
+    pub trailer_type: Option<String>, // "Trailer" or "Clip"
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct MetaLink {
+    pub name: String,
+    pub category: String,
+    pub url: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct MetaBehaviorHints {
+    // Video to play directly instead of showing an episode list
+    #[serde(default)]
+    pub default_video_id: Option<String>,
+    #[serde(default)]
+    pub has_scheduled_videos: bool,
+}
+
+// An entry of a meta's `videos`, i.e. an episode for series
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct MetaVideo {
+    pub id: String,
+    #[serde(default)]
+    pub title: Option<String>,
+    #[serde(default)]
+    pub name: Option<String>,
+    #[serde(default, deserialize_with = "lenient_u32")]
+    pub season: Option<u32>,
+    // Cinemeta uses "episode", older addons use "number"
+    #[serde(default, deserialize_with = "lenient_u32")]
+    pub episode: Option<u32>,
+    #[serde(default, deserialize_with = "lenient_u32")]
+    pub number: Option<u32>,
+    #[serde(default)]
+    pub released: Option<String>,
+    #[serde(default)]
+    pub first_aired: Option<String>,
+    #[serde(default)]
+    pub thumbnail: Option<String>,
+    #[serde(default)]
+    pub overview: Option<String>,
+    #[serde(default)]
+    pub description: Option<String>,
+}
+
+impl MetaItem {
+    fn genre_list(&self) -> Option<Vec<String>> {
+        self.genre.clone().or_else(|| self.genres.clone())
+    }
+
+    // Catalog metas often only carry releaseInfo ("2011-2019"), so fall back to its start year
+    fn year_or_release(&self) -> Option<String> {
+        self.year.clone().or_else(|| {
+            self.release_info
+                .as_deref()
+                .and_then(|r| r.split(['-', '–']).next())
+                .map(|y| y.trim().to_string())
+                .filter(|y| !y.is_empty())
+        })
+    }
+
+    fn content_type_opt(&self) -> Option<String> {
+        Some(self.content_type.clone()).filter(|t| !t.is_empty())
+    }
+}
+
+impl MetaVideo {
+    // Only videos with a season and episode number are episodes
+    pub fn to_episode(&self) -> Option<Episode> {
+        let season = self.season?;
+        let episode = self.episode.or(self.number)?;
+
+        Some(Episode {
+            id: self.id.clone(),
+            title: self
+                .name
+                .clone()
+                .or_else(|| self.title.clone())
+                .unwrap_or_else(|| format!("Episode {}", episode)),
+            season,
+            episode,
+            released: self.released.clone().or_else(|| self.first_aired.clone()),
+            thumbnail: self.thumbnail.clone(),
+            overview: self.overview.clone().or_else(|| self.description.clone()),
+        })
+    }
+}
+
+impl From<MetaItem> for Movie {
+    fn from(meta: MetaItem) -> Self {
+        Movie {
+            year: meta.year_or_release(),
+            genre: meta.genre_list(),
+            content_type: meta.content_type_opt(),
+            id: meta.id,
+            name: meta.name,
+            poster: meta.poster,
+            background: meta.background,
+            description: meta.description,
+            imdb_rating: meta.imdb_rating,
+            director: meta.director,
+            cast: meta.cast,
+            runtime: meta.runtime,
+            country: meta.country,
+            language: meta.language,
+            release_info: meta.release_info,
+            released: meta.released,
+            logo: meta.logo,
+            trailers: meta.trailers,
+            links: meta.links,
+            behavior_hints: meta.behavior_hints,
+        }
+    }
+}
+
+impl From<MetaItem> for Series {
+    fn from(meta: MetaItem) -> Self {
+        Series {
+            year: meta.year_or_release(),
+            genre: meta.genre_list(),
+            content_type: meta.content_type_opt(),
+            id: meta.id,
+            name: meta.name,
+            poster: meta.poster,
+            background: meta.background,
+            description: meta.description,
+            imdb_rating: meta.imdb_rating,
+            director: meta.director,
+            cast: meta.cast,
+            runtime: meta.runtime,
+            country: meta.country,
+            language: meta.language,
+            release_info: meta.release_info,
+            released: meta.released,
+            logo: meta.logo,
+            trailers: meta.trailers,
+            links: meta.links,
+            behavior_hints: meta.behavior_hints,
+            seasons: meta.seasons,
+            episodes: meta.episodes,
+            status: meta.status,
+            network: meta.network,
+        }
+    }
+}
+
+impl From<MetaItem> for Anime {
+    fn from(meta: MetaItem) -> Self {
+        Anime {
+            year: meta.year_or_release(),
+            genre: meta.genre_list(),
+            content_type: meta.content_type_opt(),
+            id: meta.id,
+            name: meta.name,
+            poster: meta.poster,
+            background: meta.background,
+            description: meta.description,
+            imdb_rating: meta.imdb_rating,
+            director: meta.director,
+            cast: meta.cast,
+            runtime: meta.runtime,
+            country: meta.country,
+            language: meta.language,
+            release_info: meta.release_info,
+            released: meta.released,
+            logo: meta.logo,
+            trailers: meta.trailers,
+            links: meta.links,
+            behavior_hints: meta.behavior_hints,
+            seasons: meta.seasons,
+            episodes: meta.episodes,
+            status: meta.status,
+            studio: meta.studio,
+            mal_rating: meta.mal_rating,
+            anime_type: meta.anime_type,
+        }
+    }
+}
+
+impl From<MetaItem> for SearchResult {
+    fn from(meta: MetaItem) -> Self {
+        SearchResult {
+            year: meta.year_or_release(),
+            content_type: meta.content_type_opt().unwrap_or_else(|| "movie".to_string()),
+            id: meta.id,
+            name: meta.name,
+            poster: meta.poster,
+            imdb_rating: meta.imdb_rating,
+            description: meta.description,
+        }
+    }
+}
+
+// "7.5", 7.5 and null all become Option<String>
+fn lenient_string<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<String>, D::Error> {
+    Ok(match Value::deserialize(deserializer)? {
+        Value::String(s) if !s.is_empty() => Some(s),
+        Value::Number(n) => Some(n.to_string()),
+        _ => None,
+    })
+}
+
+// 3 and "3" become Some(3); anything else is None rather than an error
+fn lenient_u32<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<u32>, D::Error> {
+    Ok(match Value::deserialize(deserializer)? {
+        Value::Number(n) => n.as_u64().and_then(|n| u32::try_from(n).ok()),
+        Value::String(s) => s.trim().parse().ok(),
+        _ => None,
+    })
+}
+
+// Lists are sometimes sent as a single comma-separated string
+fn lenient_list<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<Vec<String>>, D::Error> {
+    Ok(match Value::deserialize(deserializer)? {
+        Value::Array(items) => Some(
+            items
+                .into_iter()
+                .filter_map(|v| match v {
+                    Value::String(s) => Some(s),
+                    Value::Number(n) => Some(n.to_string()),
+                    _ => None,
+                })
+                .collect(),
+        ),
+        Value::String(s) => Some(
+            s.split(',')
+                .map(|part| part.trim().to_string())
+                .filter(|part| !part.is_empty())
+                .collect(),
+        ),
+        _ => None,
+    })
+}
+
+// Malformed entries of an array are skipped instead of failing the whole meta
+fn lenient_entries<'de, D: Deserializer<'de>, T: DeserializeOwned>(deserializer: D) -> Result<Vec<T>, D::Error> {
+    Ok(match Value::deserialize(deserializer)? {
+        Value::Array(items) => items
+            .into_iter()
+            .filter_map(|item| serde_json::from_value(item).ok())
+            .collect(),
+        _ => Vec::new(),
+    })
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct StremioResponse<T> {
     pub metas: Option<Vec<T>>,
@@ -154,30 +676,165 @@ pub struct SearchResponse {
     pub metas: Option<Vec<SearchResult>>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Addon {
     pub name: String,
     pub base_url: String,
     pub manifest: AddonManifest,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct AddonManifest {
     pub id: String,
     pub version: String,
     pub name: String,
+    #[serde(default)]
     pub description: String,
-    pub resources: Vec<String>,
+    #[serde(default)]
+    pub resources: Vec<AddonResource>,
+    #[serde(default)]
     pub types: Vec<String>,
+    #[serde(default)]
     pub catalogs: Vec<AddonCatalog>,
+    #[serde(rename = "idPrefixes", default)]
+    pub id_prefixes: Option<Vec<String>>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+// Manifests list resources either by name ("stream") or as an object that
+// narrows the types and idPrefixes for that one resource
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(untagged)]
+pub enum AddonResource {
+    Name(String),
+    Full {
+        name: String,
+        #[serde(default)]
+        types: Option<Vec<String>>,
+        #[serde(rename = "idPrefixes", default)]
+        id_prefixes: Option<Vec<String>>,
+    },
+}
+
+impl AddonResource {
+    pub fn name(&self) -> &str {
+        match self {
+            AddonResource::Name(name) => name,
+            AddonResource::Full { name, .. } => name,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct AddonCatalog {
     #[serde(rename = "type")]
     pub catalog_type: String,
     pub id: String,
+    #[serde(default)]
+    pub name: String,
+    #[serde(default)]
+    pub extra: Vec<AddonCatalogExtra>,
+    // Older manifests only list the names of supported extras
+    #[serde(rename = "extraSupported", default)]
+    pub extra_supported: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct AddonCatalogExtra {
     pub name: String,
+    #[serde(rename = "isRequired", default)]
+    pub is_required: bool,
+    #[serde(default)]
+    pub options: Option<Vec<String>>,
+}
+
+// Catalog `extra` arguments (skip, genre, search, ...), keyed by name
+pub type CatalogExtra = BTreeMap<String, String>;
+
+impl AddonCatalog {
+    pub fn supports_extra(&self, extra_name: &str) -> bool {
+        self.extra.iter().any(|e| e.name == extra_name)
+            || self.extra_supported.iter().any(|e| e == extra_name)
+    }
+
+    // Whether this catalog can be requested with these extra values: every one must be
+    // declared (and listed in its options, if any) and every required extra present
+    pub fn accepts(&self, extra: &CatalogExtra) -> bool {
+        let declared = extra.iter().all(|(name, value)| {
+            self.supports_extra(name)
+                && self
+                    .extra
+                    .iter()
+                    .find(|e| &e.name == name)
+                    .and_then(|e| e.options.as_ref())
+                    .is_none_or(|options| options.contains(value))
+        });
+
+        let required = self
+            .extra
+            .iter()
+            .filter(|e| e.is_required)
+            .all(|e| extra.contains_key(&e.name));
+
+        declared && required
+    }
+}
+
+impl Addon {
+    // Whether this addon declares `resource` for `content_type`. When an id is
+    // given it must also start with one of the addon's idPrefixes (if any).
+    pub fn supports(&self, resource: &str, content_type: &str, id: Option<&str>) -> bool {
+        let manifest = &self.manifest;
+
+        manifest.resources.iter().any(|r| {
+            if r.name() != resource {
+                return false;
+            }
+
+            let (types, id_prefixes) = match r {
+                AddonResource::Name(_) => (&manifest.types, manifest.id_prefixes.as_ref()),
+                AddonResource::Full { types, id_prefixes, .. } => (
+                    types.as_ref().unwrap_or(&manifest.types),
+                    id_prefixes.as_ref().or(manifest.id_prefixes.as_ref()),
+                ),
+            };
+
+            if !types.iter().any(|t| t == content_type) {
+                return false;
+            }
+
+            match (id, id_prefixes) {
+                (Some(id), Some(prefixes)) => prefixes.iter().any(|p| id.starts_with(p.as_str())),
+                _ => true,
+            }
+        })
+    }
+
+    pub fn catalog(&self, content_type: &str, catalog_id: &str) -> Option<&AddonCatalog> {
+        self.manifest
+            .catalogs
+            .iter()
+            .find(|c| c.catalog_type == content_type && c.id == catalog_id)
+    }
+}
+
+// An addon catalog shown as a row on the home screen
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct HomeRow {
+    pub id: String, // "{addon_id}/{type}/{catalog_id}"
+    pub name: String,
+    pub addon_id: String,
+    pub addon_name: String,
+    pub content_type: String,
+    pub catalog_id: String,
+    pub hidden: bool,
+    pub items: Vec<SearchResult>,
+}
+
+// Saved show/hide state of a home row; the list order is the row order
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct HomeRowSetting {
+    pub id: String,
+    pub hidden: bool,
 }
 
 // Continue watching item for localStorage persistence
@@ -191,4 +848,135 @@ pub struct ContinueWatchingItem {
     pub last_watched: String, // ISO timestamp
     pub season: Option<u32>,
     pub episode: Option<u32>,
-}
\ No newline at end of file
+}
+// Live statistics of the torrent file being streamed, emitted as "stream-stats"
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct StreamStats {
+    pub info_hash: String,
+    pub name: Option<String>,
+    pub file_index: usize,
+    pub file_name: String,
+    pub file_size: u64,
+    pub downloaded_bytes: u64, // of the selected file
+    pub progress: f64, // 0.0 to 1.0
+    pub download_speed: u64, // bytes per second
+    pub upload_speed: u64, // bytes per second
+    pub peers: usize,
+    pub pieces_verified: u64, // pieces of the file downloaded and hash-checked
+    pub pieces_total: u64, // pieces covering the selected file
+    pub playhead_bytes: u64, // last position the player read
+    pub buffered_ahead_bytes: u64, // downloaded without gaps from the playhead
+    pub buffered_ahead_secs: Option<f64>, // once the player's read rate is known
+    pub eta_secs: Option<u64>, // until the file is complete, at the current speed
+    pub finished: bool,
+}
+
+// Why a torrent stream couldn't start
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum StartupFailure {
+    MetadataTimeout, // no peer sent the torrent's metadata in time
+    NoFiles,         // the torrent has no file to play
+    NoPeers,         // metadata arrived but no peer stayed connected
+    BufferStalled,   // peers connected but the first pieces stopped arriving
+    Engine,          // the torrent engine itself failed
+    Cancelled,       // the stream was stopped or replaced before it started
+}
+
+// Where a torrent stream is in its startup
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "state", rename_all = "snake_case")]
+pub enum StartupState {
+    ResolvingMetadata,
+    ConnectingPeers { peers: usize },
+    Buffering { buffered_bytes: u64, target_bytes: u64, peers: usize },
+    Ready { url: String },
+    Failed { reason: StartupFailure, message: String },
+}
+
+// Emitted as "torrent-startup" on every startup state change and buffering update
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TorrentStartup {
+    pub info_hash: String,
+    #[serde(flatten)]
+    pub state: StartupState,
+}
+
+// A season and episode of a series, used to find its file in a season pack
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub struct EpisodeRef {
+    pub season: u32,
+    pub episode: u32,
+}
+
+// One file of a torrent, listed once its metadata is resolved
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TorrentFile {
+    pub index: usize, // index in the torrent, as used by fileIdx
+    pub path: String, // relative to the torrent's folder
+    pub size: u64,
+    pub is_video: bool,
+    pub season: Option<u32>, // parsed from the path
+    pub episode: Option<u32>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TorrentFileList {
+    pub info_hash: String,
+    pub name: Option<String>,
+    pub files: Vec<TorrentFile>, // in torrent order, without padding files
+    pub selected: Option<usize>, // the file that plays unless another one is picked
+}
+
+// Where torrent data is downloaded and how much of it is kept
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TorrentCacheSettings {
+    pub directory: Option<String>, // None keeps it in the app cache directory
+    pub max_size_bytes: u64,
+}
+
+// One torrent's downloaded data
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TorrentCacheEntry {
+    pub info_hash: String,
+    pub size_bytes: u64, // space used on disk
+    pub last_used: u64, // unix seconds
+    pub in_use: bool, // streaming or downloading; never evicted
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TorrentCacheStatus {
+    pub directory: String,
+    pub max_size_bytes: u64,
+    pub used_bytes: u64,
+    pub entries: Vec<TorrentCacheEntry>, // least recently used first
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum DownloadStatus {
+    Downloading,
+    Paused,
+    Verifying, // all data is in; checking the file before it's marked complete
+    Completed,
+    Failed,
+}
+
+// One entry of the Downloads library, emitted as "download-progress" whenever it changes
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Download {
+    pub id: String,
+    pub title: String,
+    pub stream: Stream, // what was queued; torrents by info hash, direct streams by URL
+    #[serde(default)]
+    pub episode: Option<EpisodeRef>, // picks the file of a season pack queued without a file index
+    pub status: DownloadStatus,
+    pub file_index: Option<usize>, // for torrents, once the file is known
+    pub file_path: Option<String>, // where the video is (or will be) on disk
+    pub total_bytes: Option<u64>,
+    pub downloaded_bytes: u64,
+    pub download_speed: u64, // bytes per second
+    pub error: Option<String>,
+    pub added_at: u64, // unix seconds
+    pub completed_at: Option<u64>,
+}
@@ -0,0 +1,422 @@
+use crate::models::ReleaseInfo;
+use regex::Regex;
+use std::sync::OnceLock;
+
+// (pattern, label) pairs, checked in order; the first match wins.
+// Patterns are matched as whole tokens against the upper-cased release name.
+const RESOLUTIONS: &[(&str, &str)] = &[
+    (r"2160P", "2160p"),
+    (r"1440P", "1440p"),
+    (r"1080P|1080I", "1080p"),
+    (r"720P", "720p"),
+    (r"576P", "576p"),
+    (r"480P", "480p"),
+];
+
+// Only used without an explicit resolution: "UHD BluRay 1080p" is a 1080p encode
+const RESOLUTION_ALIASES: &[(&str, &str)] = &[
+    (r"4K|UHD", "2160p"),
+    (r"2K", "1440p"),
+    (r"FHD", "1080p"),
+];
+
+const SOURCES: &[(&str, &str)] = &[
+    (r"REMUX|BDREMUX", "REMUX"),
+    (r"HD-?CAM|CAM-?RIP|CAM", "CAM"),
+    (r"HD-?TS|TELESYNC|TS|PDVD", "TS"),
+    (r"HD-?TC|TELECINE|TC", "TC"),
+    (r"DVD-?SCR|SCREENER|SCR", "SCR"),
+    (r"BLU-?RAY|BDRIP|BRRIP|BD25|BD50|BDMV", "BluRay"),
+    (r"WEB-?RIP", "WEBRip"),
+    (r"WEB-?DL|WEB", "WEB-DL"),
+    (r"HDTV|PDTV", "HDTV"),
+    (r"HDRIP", "HDRip"),
+    (r"DVD-?RIP|DVD5|DVD9|DVD", "DVD"),
+];
+
+const VIDEO_CODECS: &[(&str, &str)] = &[
+    (r"X\.?265|H\.?265|HEVC", "HEVC"),
+    (r"X\.?264|H\.?264|AVC", "AVC"),
+    (r"AV1", "AV1"),
+    (r"VP9", "VP9"),
+    (r"XVID|DIVX", "XviD"),
+];
+
+const AUDIO_CODECS: &[(&str, &str)] = &[
+    (r"TRUE-?HD", "TrueHD"),
+    (r"DTS-?HD[ .-]?MA", "DTS-HD MA"),
+    (r"DTS[ .-]?X", "DTS:X"),
+    (r"DTS-?HD", "DTS-HD"),
+    (r"DTS", "DTS"),
+    (r"DDP|DD\+|E-?AC-?3", "DD+"),
+    (r"DD|AC-?3", "DD"),
+    (r"AAC", "AAC"),
+    (r"FLAC", "FLAC"),
+    (r"OPUS", "Opus"),
+    (r"MP3", "MP3"),
+];
+
+const LANGUAGE_WORDS: &[(&str, &str)] = &[
+    (r"MULTI", "Multi"),
+    (r"DUAL(?:[ .-]?AUDIO)?", "Dual Audio"),
+    (r"ENG|ENGLISH", "English"),
+    (r"FRENCH|TRUEFRENCH|VFF|VFQ|VF2?", "French"),
+    (r"ITA|ITALIAN", "Italian"),
+    (r"GER|GERMAN", "German"),
+    // Not "SPA", which is also a word ("Spa.Night.2016")
+    (r"SPANISH|ESP|CASTELLANO", "Spanish"),
+    (r"LATINO", "Latino"),
+    (r"POR|PORTUGUESE", "Portuguese"),
+    (r"RUS|RUSSIAN", "Russian"),
+    (r"JAP|JAPANESE", "Japanese"),
+    (r"KOR|KOREAN", "Korean"),
+    (r"CHI|CHINESE", "Chinese"),
+    (r"HINDI", "Hindi"),
+];
+
+// Torrentio lists audio languages as flag emoji
+const LANGUAGE_FLAGS: &[(&str, &str)] = &[
+    ("🇬🇧", "English"),
+    ("🇺🇸", "English"),
+    ("🇫🇷", "French"),
+    ("🇪🇸", "Spanish"),
+    ("🇲🇽", "Latino"),
+    ("🇩🇪", "German"),
+    ("🇮🇹", "Italian"),
+    ("🇵🇹", "Portuguese"),
+    ("🇧🇷", "Portuguese"),
+    ("🇷🇺", "Russian"),
+    ("🇺🇦", "Ukrainian"),
+    ("🇵🇱", "Polish"),
+    ("🇳🇱", "Dutch"),
+    ("🇹🇷", "Turkish"),
+    ("🇸🇪", "Swedish"),
+    ("🇯🇵", "Japanese"),
+    ("🇰🇷", "Korean"),
+    ("🇨🇳", "Chinese"),
+    ("🇹🇼", "Chinese"),
+    ("🇮🇳", "Hindi"),
+    ("🇸🇦", "Arabic"),
+];
+
+// Words that end a release name after a dash but aren't a group ("WEB-DL", "BD-Rip")
+const NOT_GROUPS: &[&str] = &["DL", "RIP", "HD", "RAY", "X", "MA", "AUDIO"];
+
+struct Patterns {
+    resolutions: Vec<(Regex, &'static str)>,
+    resolution_aliases: Vec<(Regex, &'static str)>,
+    sources: Vec<(Regex, &'static str)>,
+    video_codecs: Vec<(Regex, &'static str)>,
+    audio_codecs: Vec<(Regex, &'static str)>,
+    languages: Vec<(Regex, &'static str)>,
+    bit_depth: Regex,
+    channels: Regex,
+    atmos: Regex,
+    dolby_vision: Regex,
+    hdr10_plus: Regex,
+    hdr10: Regex,
+    hdr: Regex,
+    hlg: Regex,
+    season_episode: Regex,
+    cross_episode: Regex,
+    season: Regex,
+    episode: Regex,
+    leading_episode: Regex,
+    numbered_episode: Regex,
+    group_dash: Regex,
+    group_bracket: Regex,
+    group_leading: Regex,
+    crc: Regex,
+}
+
+fn token(pattern: &str) -> Regex {
+    Regex::new(&format!(r"(?:^|[^A-Z0-9])(?:{})(?:[^A-Z0-9]|$)", pattern)).expect("invalid release pattern")
+}
+
+fn table(entries: &[(&str, &'static str)]) -> Vec<(Regex, &'static str)> {
+    entries.iter().map(|(pattern, label)| (token(pattern), *label)).collect()
+}
+
+// Audio codecs are usually glued to their channel count ("DDP5.1", "AAC2.0")
+fn audio_table(entries: &[(&str, &'static str)]) -> Vec<(Regex, &'static str)> {
+    entries
+        .iter()
+        .map(|(pattern, label)| {
+            let re = Regex::new(&format!(r"(?:^|[^A-Z0-9])(?:{})(?:[^A-Z]|$)", pattern)).expect("invalid release pattern");
+            (re, *label)
+        })
+        .collect()
+}
+
+fn patterns() -> &'static Patterns {
+    static PATTERNS: OnceLock<Patterns> = OnceLock::new();
+    PATTERNS.get_or_init(|| Patterns {
+        resolutions: table(RESOLUTIONS),
+        resolution_aliases: table(RESOLUTION_ALIASES),
+        sources: table(SOURCES),
+        video_codecs: table(VIDEO_CODECS),
+        audio_codecs: audio_table(AUDIO_CODECS),
+        languages: table(LANGUAGE_WORDS),
+        bit_depth: Regex::new(r"(?:^|[^A-Z0-9])(8|10|12)[ .-]?BITS?(?:[^A-Z0-9]|$)|HI10P").unwrap(),
+        // "DDP5.1", "AAC 2.0", "7.1" but not "2019.1080p"
+        channels: Regex::new(r"(?:^|[^0-9])([12567])[ .]([01])(?:CH)?(?:[^0-9]|$)").unwrap(),
+        atmos: token(r"ATMOS"),
+        dolby_vision: token(r"DV|DOVI|DOLBY[ .-]?VISION"),
+        hdr10_plus: Regex::new(r"(?:^|[^A-Z0-9])(?:HDR10\+|HDR10PLUS)").unwrap(),
+        hdr10: token(r"HDR10"),
+        hdr: token(r"HDR"),
+        hlg: token(r"HLG"),
+        season_episode: Regex::new(r"(?:^|[^A-Z0-9])S(\d{1,2})[ .-]?E(\d{1,3})(?:[^0-9]|$)").unwrap(),
+        cross_episode: Regex::new(r"(?:^|[^A-Z0-9])(\d{1,2})X(\d{2,3})(?:[^A-Z0-9]|$)").unwrap(),
+        season: Regex::new(r"(?:^|[^A-Z0-9])(?:S|SEASON[ .]?)(\d{1,2})(?:[^A-Z0-9]|$)").unwrap(),
+        // "E05", "EP05", "Ep 5", "Episode 5" or the anime style "Show - 05", but not "Show - 720p"
+        episode: Regex::new(r"(?:^|[^A-Z0-9])(?:E|EP[ .]?|EPISODE[ .]?)(\d{1,3})(?:[^0-9PI]|$)|\s-\s(\d{1,3})(?:[^0-9PI]|$)").unwrap(),
+        // File names that start with the episode: "03 - Title", "03. Title"
+        leading_episode: Regex::new(r"^\s*(\d{1,3})\s*[-.]").unwrap(),
+        // Season and episode as one number: "Show.101" is S01E01
+        numbered_episode: Regex::new(r"(?:^|[^A-Z0-9])(\d)(\d{2})(?:[^A-Z0-9]|$)").unwrap(),
+        group_dash: Regex::new(r"-([A-Za-z0-9]+)(?:\[[^\]]*\])?\s*$").unwrap(),
+        group_bracket: Regex::new(r"\[([^\]\[]+)\]\s*$").unwrap(),
+        group_leading: Regex::new(r"^\s*\[([^\]\[]+)\]").unwrap(),
+        // Anime releases end in the file's CRC32, e.g. "[ABCD1234]"
+        crc: Regex::new(r"^[0-9A-Fa-f]{8}$").unwrap(),
+    })
+}
+
+fn first_label(table: &[(Regex, &'static str)], text: &str) -> Option<String> {
+    table
+        .iter()
+        .find(|(re, _)| re.is_match(text))
+        .map(|(_, label)| label.to_string())
+}
+
+// Parse a stream title such as "Movie.2019.2160p.WEB-DL.DDP5.1.Atmos.DV.HEVC-GROUP\n👤 12 💾 5 GB 🇬🇧 / 🇫🇷".
+// The first line is the release name; flags and other details may follow on later lines.
+pub fn parse(title: &str) -> ReleaseInfo {
+    let p = patterns();
+
+    let release_name = title.lines().next().unwrap_or("").trim();
+    let release_name = strip_extension(release_name);
+    let name = release_name.replace('_', " ").to_uppercase();
+
+    let mut info = ReleaseInfo {
+        resolution: first_label(&p.resolutions, &name).or_else(|| first_label(&p.resolution_aliases, &name)),
+        source: first_label(&p.sources, &name),
+        video_codec: first_label(&p.video_codecs, &name),
+        audio_codec: first_label(&p.audio_codecs, &name),
+        atmos: p.atmos.is_match(&name),
+        ..ReleaseInfo::default()
+    };
+
+    info.bit_depth = p.bit_depth.captures(&name).map(|c| {
+        c.get(1).and_then(|m| m.as_str().parse().ok()).unwrap_or(10)
+    });
+
+    info.audio_channels = p
+        .channels
+        .captures(&name)
+        .map(|c| format!("{}.{}", &c[1], &c[2]));
+
+    if p.dolby_vision.is_match(&name) {
+        info.hdr.push("DV".to_string());
+    }
+    if p.hdr10_plus.is_match(&name) {
+        info.hdr.push("HDR10+".to_string());
+    } else if p.hdr10.is_match(&name) {
+        info.hdr.push("HDR10".to_string());
+    } else if p.hdr.is_match(&name) {
+        info.hdr.push("HDR".to_string());
+    }
+    if p.hlg.is_match(&name) {
+        info.hdr.push("HLG".to_string());
+    }
+
+    if let Some(c) = p.season_episode.captures(&name).or_else(|| p.cross_episode.captures(&name)) {
+        info.season = c[1].parse().ok();
+        info.episode = c[2].parse().ok();
+    } else {
+        info.season = p.season.captures(&name).and_then(|c| c[1].parse().ok());
+        info.episode = episode_number(&name);
+    }
+
+    // Languages come from words in the release name and flags anywhere in the title
+    for (re, language) in &p.languages {
+        if re.is_match(&name) {
+            push_unique(&mut info.languages, language);
+        }
+    }
+    for (flag, language) in LANGUAGE_FLAGS {
+        if title.contains(flag) {
+            push_unique(&mut info.languages, language);
+        }
+    }
+
+    info.release_group = release_group(release_name);
+
+    info
+}
+
+// Season and episode of a file in a torrent, e.g. "Show.S01E03.1080p.mkv" or
+// "Season 2/[Group] Show - 05 [1080p].mkv" where the season comes from the folder
+pub fn file_episode(path: &str) -> (Option<u32>, Option<u32>) {
+    let p = patterns();
+
+    let mut components = path.rsplit(['/', '\\']);
+    let file_name = strip_extension(components.next().unwrap_or(""));
+    let name = file_name.replace('_', " ").to_uppercase();
+
+    if let Some(c) = p.season_episode.captures(&name).or_else(|| p.cross_episode.captures(&name)) {
+        return (c[1].parse().ok(), c[2].parse().ok());
+    }
+
+    let season: Option<u32> = std::iter::once(file_name)
+        .chain(components)
+        .find_map(|part| p.season.captures(&part.replace('_', " ").to_uppercase()).and_then(|c| c[1].parse().ok()));
+    let episode = episode_number(&name)
+        .or_else(|| {
+            // Only trusted when it starts with the folder's season, so "Show.720" in season 1 isn't episode 20
+            let season = season?;
+            p.numbered_episode
+                .captures_iter(&name)
+                .find(|c| c[1].parse() == Ok(season))
+                .and_then(|c| c[2].parse().ok())
+        })
+        .or_else(|| p.leading_episode.captures(&name).and_then(|c| c[1].parse().ok()));
+    (season, episode)
+}
+
+// An episode without a season: "E05", "Episode 5" or "Show - 05"
+fn episode_number(name: &str) -> Option<u32> {
+    patterns()
+        .episode
+        .captures(name)
+        .and_then(|c| c.get(1).or_else(|| c.get(2)))
+        .and_then(|m| m.as_str().parse().ok())
+}
+
+fn push_unique(list: &mut Vec<String>, value: &str) {
+    if !list.iter().any(|v| v == value) {
+        list.push(value.to_string());
+    }
+}
+
+fn strip_extension(name: &str) -> &str {
+    for ext in [".mkv", ".mp4", ".avi", ".m4v", ".webm", ".ts"] {
+        let stem_len = name.len().saturating_sub(ext.len());
+        if stem_len > 0 && name.get(stem_len..).is_some_and(|tail| tail.eq_ignore_ascii_case(ext)) {
+            return &name[..stem_len];
+        }
+    }
+    name
+}
+
+fn release_group(release_name: &str) -> Option<String> {
+    let p = patterns();
+
+    // Anime groups lead: "[SubsPlease] Show - 05 (1080p) [ABCD1234]"
+    if let Some(group) = p
+        .group_leading
+        .captures(release_name)
+        .map(|c| c[1].trim().to_string())
+        .filter(|group| is_group(group))
+    {
+        return Some(group);
+    }
+
+    if let Some(c) = p.group_dash.captures(release_name) {
+        let group = &c[1];
+        if !NOT_GROUPS.contains(&group.to_uppercase().as_str()) && !is_tag(group) {
+            return Some(group.to_string());
+        }
+    }
+
+    // "Movie (2010) [1080p] [YTS.MX]"
+    p.group_bracket
+        .captures(release_name)
+        .map(|c| c[1].trim().to_string())
+        .filter(|group| is_group(group))
+}
+
+fn is_group(token: &str) -> bool {
+    !token.is_empty() && !is_tag(token) && !patterns().crc.is_match(token)
+}
+
+// A bracketed or trailing token that describes the release rather than naming the group
+fn is_tag(token: &str) -> bool {
+    let upper = token.to_uppercase();
+    let p = patterns();
+    [&p.resolutions, &p.resolution_aliases, &p.sources, &p.video_codecs, &p.audio_codecs]
+        .iter()
+        .any(|table| table.iter().any(|(re, _)| re.is_match(&upper)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ts_source_is_a_whole_token() {
+        assert_eq!(parse("Tsunami.2020.1080p.WEB-DL.x264-GRP").source.as_deref(), Some("WEB-DL"));
+        assert_eq!(parse("Movie.2020.HDTS.x264-GRP").source.as_deref(), Some("TS"));
+        assert_eq!(parse("Movie 2020 TS XviD").source.as_deref(), Some("TS"));
+    }
+
+    #[test]
+    fn explicit_resolution_beats_uhd() {
+        assert_eq!(parse("Movie 2020 UHD BluRay 1080p DTS-HD MA 5.1 REMUX").resolution.as_deref(), Some("1080p"));
+        assert_eq!(parse("Movie.2020.UHD.BluRay.x265-GRP").resolution.as_deref(), Some("2160p"));
+        assert_eq!(parse("Movie.2020.4K.HDR.x265-GRP").resolution.as_deref(), Some("2160p"));
+    }
+
+    #[test]
+    fn anime_release() {
+        let info = parse("[SubsPlease] Frieren - 05 (1080p) [ABCD1234].mkv");
+        assert_eq!(info.release_group.as_deref(), Some("SubsPlease"));
+        assert_eq!(info.episode, Some(5));
+        assert_eq!(info.resolution.as_deref(), Some("1080p"));
+        assert_eq!(parse("Show - 720p WEB").episode, None);
+    }
+
+    #[test]
+    fn release_groups() {
+        assert_eq!(parse("Movie.2019.2160p.WEB-DL.DDP5.1.Atmos.DV.HEVC-GROUP").release_group.as_deref(), Some("GROUP"));
+        assert_eq!(parse("Movie (2010) [1080p] [YTS.MX]").release_group.as_deref(), Some("YTS.MX"));
+        assert_eq!(parse("Movie.2019.1080p.WEB-DL").release_group, None);
+    }
+
+    #[test]
+    fn spa_is_not_spanish() {
+        assert!(parse("Spa.Night.2016.1080p.WEBRip.x264-GRP").languages.is_empty());
+        assert_eq!(parse("Movie.2016.SPANISH.1080p.WEBRip.x264-GRP").languages, vec!["Spanish"]);
+    }
+
+    #[test]
+    fn strip_extension_handles_non_ascii() {
+        assert_eq!(strip_extension("Movie.MKV"), "Movie");
+        // The Kelvin sign lower-cases to "k" but is three bytes long
+        assert_eq!(strip_extension("x.m\u{212A}v"), "x.m\u{212A}v");
+        assert_eq!(strip_extension("Ünïcödé.mp4"), "Ünïcödé");
+        assert_eq!(strip_extension(".mkv"), ".mkv");
+    }
+
+    #[test]
+    fn file_episode_layouts() {
+        assert_eq!(file_episode("Show.S01/Show.S01E03.1080p.mkv"), (Some(1), Some(3)));
+        assert_eq!(file_episode("Show 1x04.mkv"), (Some(1), Some(4)));
+        assert_eq!(file_episode("Season 2/[Group] Show - 05 [1080p].mkv"), (Some(2), Some(5)));
+        assert_eq!(file_episode("Season_03/Show_E12.mkv"), (Some(3), Some(12)));
+        assert_eq!(file_episode("Season 2/03 - Title.mkv"), (Some(2), Some(3)));
+        assert_eq!(file_episode("Show.S01/Show.101.mkv"), (Some(1), Some(1)));
+        assert_eq!(file_episode("ep 7.mkv"), (None, Some(7)));
+        assert_eq!(file_episode("Show/Episode 7.mp4"), (None, Some(7)));
+    }
+
+    #[test]
+    fn file_episode_ignores_other_numbers() {
+        assert_eq!(file_episode("Show.2019.1080p.x264.mkv"), (None, None));
+        assert_eq!(file_episode("Season 1/Show.720p.mkv"), (Some(1), None));
+        assert_eq!(file_episode("Show.S01/Show.205.mkv"), (Some(1), None));
+        assert_eq!(file_episode("Show.101.mkv"), (None, None));
+        assert_eq!(file_episode("2019.Show.mkv"), (None, None));
+    }
+}
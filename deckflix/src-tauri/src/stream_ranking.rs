@@ -0,0 +1,341 @@
+use crate::models::{RankingProfile, RankingSettings, Stream};
+use std::cmp::Ordering;
+
+pub const STEAM_DECK_PROFILE: &str = "steam_deck";
+
+// Lowest to highest; the index is used to compare resolutions
+const RESOLUTIONS: [&str; 6] = ["480p", "576p", "720p", "1080p", "1440p", "2160p"];
+
+// Score for a resolution when the profile has no preference
+const RESOLUTION_SCORES: [f64; 6] = [15.0, 18.0, 35.0, 45.0, 52.0, 60.0];
+
+// How a single stream fares against a profile. Streams that break a hard limit
+// (max resolution, size, seeders) are still listed, after every acceptable one.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct StreamRank {
+    pub acceptable: bool,
+    pub score: f64,
+}
+
+pub fn default_settings() -> RankingSettings {
+    RankingSettings {
+        active: STEAM_DECK_PROFILE.to_string(),
+        profiles: vec![
+            // 800p screen over Wi-Fi: 1080p is plenty and HEVC decoding costs battery
+            RankingProfile {
+                id: STEAM_DECK_PROFILE.to_string(),
+                name: "Steam Deck".to_string(),
+                preferred_resolution: Some("1080p".to_string()),
+                max_resolution: Some("1080p".to_string()),
+                max_size_gb: Some(4.0),
+                preferred_codecs: vec!["AVC".to_string()],
+                avoided_codecs: vec!["HEVC".to_string()],
+                min_seeders: Some(3),
+                ..RankingProfile::default()
+            },
+            // Docked to a 4K TV on a fast connection
+            RankingProfile {
+                id: "best_quality".to_string(),
+                name: "Best quality".to_string(),
+                preferred_resolution: Some("2160p".to_string()),
+                preferred_codecs: vec!["HEVC".to_string()],
+                min_seeders: Some(1),
+                ..RankingProfile::default()
+            },
+        ],
+    }
+}
+
+fn resolution_index(resolution: Option<&str>) -> Option<usize> {
+    let resolution = resolution?.to_lowercase();
+    RESOLUTIONS.iter().position(|r| *r == resolution)
+}
+
+fn contains_ignore_case(list: &[String], value: &str) -> bool {
+    list.iter().any(|v| v.eq_ignore_ascii_case(value))
+}
+
+// "5.09 GB", "700 MB", "1.2TB" or a plain byte count, in GB
+pub fn parse_size_gb(size: &str) -> Option<f64> {
+    let upper = size.to_uppercase();
+    let number = |unit: &str| upper.replace(unit, "").trim().parse::<f64>().ok();
+
+    if upper.contains("TB") {
+        number("TB").map(|tb| tb * 1024.0)
+    } else if upper.contains("GB") {
+        number("GB")
+    } else if upper.contains("MB") {
+        number("MB").map(|mb| mb / 1024.0)
+    } else if upper.contains("KB") {
+        number("KB").map(|kb| kb / (1024.0 * 1024.0))
+    } else {
+        size.trim().parse::<f64>().ok().map(|bytes| bytes / (1024.0 * 1024.0 * 1024.0))
+    }
+}
+
+fn stream_size_gb(stream: &Stream) -> Option<f64> {
+    stream
+        .size
+        .as_deref()
+        .and_then(parse_size_gb)
+        .or_else(|| {
+            stream
+                .behavior_hints
+                .as_ref()
+                .and_then(|h| h.video_size)
+                .map(|bytes| bytes as f64 / (1024.0 * 1024.0 * 1024.0))
+        })
+}
+
+fn is_blocked(profile: &RankingProfile, stream: &Stream) -> bool {
+    if profile.blocked_terms.is_empty() {
+        return false;
+    }
+
+    let text = format!("{} {}", stream.name.as_deref().unwrap_or(""), stream.title).to_lowercase();
+    profile
+        .blocked_terms
+        .iter()
+        .map(|term| term.trim().to_lowercase())
+        .any(|term| !term.is_empty() && text.contains(&term))
+}
+
+// None when the stream contains a blocked term and must not be shown at all
+pub fn evaluate(profile: &RankingProfile, stream: &Stream) -> Option<StreamRank> {
+    if is_blocked(profile, stream) {
+        return None;
+    }
+
+    let release = &stream.release;
+    let mut acceptable = true;
+    let mut score = 0.0;
+
+    // Resolution: closest to the preferred one wins, anything above the max is out
+    let resolution = resolution_index(release.resolution.as_deref());
+    let preferred = resolution_index(profile.preferred_resolution.as_deref());
+    let max = resolution_index(profile.max_resolution.as_deref());
+
+    if let (Some(resolution), Some(max)) = (resolution, max) {
+        if resolution > max {
+            acceptable = false;
+        }
+    }
+
+    score += match (resolution, preferred) {
+        (Some(r), Some(p)) if r >= p => (50.0 - 8.0 * (r - p) as f64).max(0.0),
+        (Some(r), Some(p)) => (50.0 - 12.0 * (p - r) as f64).max(0.0),
+        (Some(r), None) => RESOLUTION_SCORES[r],
+        (None, _) => 20.0,
+    };
+
+    // Source
+    score += if release.is_low_quality_source() {
+        -40.0
+    } else {
+        match release.source.as_deref() {
+            Some("REMUX") => 8.0,
+            Some("BluRay") => 6.0,
+            Some("WEB-DL") => 4.0,
+            Some("WEBRip" | "HDRip") => 2.0,
+            _ => 0.0,
+        }
+    };
+
+    // Codec
+    if let Some(codec) = release.video_codec.as_deref() {
+        if contains_ignore_case(&profile.preferred_codecs, codec) {
+            score += 10.0;
+        }
+        if contains_ignore_case(&profile.avoided_codecs, codec) {
+            score -= 25.0;
+        }
+    }
+
+    // Languages: releases without language tags are usually English-only
+    if !profile.preferred_languages.is_empty() && !release.languages.is_empty() {
+        let matches = release
+            .languages
+            .iter()
+            .any(|l| contains_ignore_case(&profile.preferred_languages, l));
+        let multi = release.languages.iter().any(|l| l == "Multi" || l == "Dual Audio");
+
+        score += if matches {
+            15.0
+        } else if multi {
+            5.0
+        } else {
+            -10.0
+        };
+    }
+
+    // Size
+    if let Some(size) = stream_size_gb(stream) {
+        let too_small = profile.min_size_gb.is_some_and(|min| size < min);
+        let too_large = profile.max_size_gb.is_some_and(|max| size > max);
+        if too_small || too_large {
+            acceptable = false;
+        } else if profile.min_size_gb.is_some() || profile.max_size_gb.is_some() {
+            score += 5.0;
+        }
+        if size < 0.5 {
+            score -= 5.0; // Probably a sample or a broken release
+        }
+    }
+
+    // Seeders: more is faster, with diminishing returns
+    if let Some(seeders) = stream.seeders {
+        if profile.min_seeders.is_some_and(|min| seeders < min) {
+            acceptable = false;
+        }
+        score += ((seeders as f64 + 1.0).ln() * 3.0).min(15.0);
+    }
+
+    Some(StreamRank { acceptable, score })
+}
+
+// Drops blocked streams and sorts the rest best first. Equal streams keep their order.
+pub fn rank(profile: &RankingProfile, streams: Vec<Stream>) -> Vec<Stream> {
+    let mut ranked: Vec<(StreamRank, Stream)> = streams
+        .into_iter()
+        .filter_map(|stream| evaluate(profile, &stream).map(|rank| (rank, stream)))
+        .collect();
+
+    ranked.sort_by(|(a, _), (b, _)| {
+        b.acceptable
+            .cmp(&a.acceptable)
+            .then_with(|| b.score.partial_cmp(&a.score).unwrap_or(Ordering::Equal))
+    });
+
+    ranked.into_iter().map(|(_, stream)| stream).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::release_parser;
+
+    fn stream(title: &str, size: &str, seeders: u32) -> Stream {
+        Stream {
+            name: Some("Torrentio".to_string()),
+            title: title.to_string(),
+            url: None,
+            info_hash: Some(title.len().to_string()),
+            file_idx: None,
+            sources: Vec::new(),
+            behavior_hints: None,
+            quality: None,
+            size: Some(size.to_string()),
+            seeders: Some(seeders),
+            leechers: None,
+            source: Some("torrent".to_string()),
+            release: release_parser::parse(title),
+            addons: vec!["Torrentio".to_string()],
+            language: None,
+            subtitles: None,
+        }
+    }
+
+    fn profile(id: &str) -> RankingProfile {
+        default_settings()
+            .profiles
+            .into_iter()
+            .find(|p| p.id == id)
+            .expect("default profile")
+    }
+
+    fn titles(streams: &[Stream]) -> Vec<&str> {
+        streams.iter().map(|s| s.title.as_str()).collect()
+    }
+
+    #[test]
+    fn steam_deck_prefers_small_1080p_avc() {
+        let ranked = rank(&profile(STEAM_DECK_PROFILE), vec![
+            stream("Movie.2019.2160p.WEB-DL.HEVC-A", "15 GB", 200),
+            stream("Movie.2019.1080p.WEB-DL.HEVC-B", "2 GB", 200),
+            stream("Movie.2019.1080p.WEB-DL.x264-C", "3.5 GB", 50),
+            stream("Movie.2019.720p.WEB-DL.x264-D", "1.2 GB", 50),
+        ]);
+
+        assert_eq!(titles(&ranked), vec![
+            "Movie.2019.1080p.WEB-DL.x264-C",
+            "Movie.2019.720p.WEB-DL.x264-D",
+            "Movie.2019.1080p.WEB-DL.HEVC-B",
+            "Movie.2019.2160p.WEB-DL.HEVC-A",
+        ]);
+    }
+
+    #[test]
+    fn best_quality_prefers_2160p() {
+        let ranked = rank(&profile("best_quality"), vec![
+            stream("Movie.2019.1080p.BluRay.x264-A", "10 GB", 100),
+            stream("Movie.2019.2160p.BluRay.HEVC-B", "40 GB", 100),
+        ]);
+
+        assert_eq!(ranked[0].title, "Movie.2019.2160p.BluRay.HEVC-B");
+    }
+
+    #[test]
+    fn hard_limits_sort_last_but_are_kept() {
+        let deck = profile(STEAM_DECK_PROFILE);
+        let oversized = stream("Movie.2019.1080p.BluRay.x264-A", "12 GB", 500);
+        let fitting = stream("Movie.2019.720p.HDTV.x264-B", "1 GB", 5);
+
+        assert!(!evaluate(&deck, &oversized).unwrap().acceptable);
+        assert!(evaluate(&deck, &fitting).unwrap().acceptable);
+
+        let ranked = rank(&deck, vec![oversized, fitting]);
+        assert_eq!(titles(&ranked), vec!["Movie.2019.720p.HDTV.x264-B", "Movie.2019.1080p.BluRay.x264-A"]);
+    }
+
+    #[test]
+    fn min_seeders_is_a_hard_limit() {
+        let deck = profile(STEAM_DECK_PROFILE);
+        let dead = stream("Movie.2019.1080p.WEB-DL.x264-A", "2 GB", 1);
+        assert!(!evaluate(&deck, &dead).unwrap().acceptable);
+    }
+
+    #[test]
+    fn blocked_terms_remove_streams() {
+        let mut deck = profile(STEAM_DECK_PROFILE);
+        deck.blocked_terms = vec!["hdcam".to_string(), "  ".to_string()];
+
+        let ranked = rank(&deck, vec![
+            stream("Movie.2019.HDCAM.x264-A", "1 GB", 100),
+            stream("Movie.2019.1080p.WEB-DL.x264-B", "2 GB", 100),
+        ]);
+
+        assert_eq!(titles(&ranked), vec!["Movie.2019.1080p.WEB-DL.x264-B"]);
+    }
+
+    #[test]
+    fn preferred_language_wins() {
+        let mut deck = profile(STEAM_DECK_PROFILE);
+        deck.preferred_languages = vec!["French".to_string()];
+
+        let ranked = rank(&deck, vec![
+            stream("Movie.2019.1080p.WEB-DL.ITALIAN.x264-A", "2 GB", 100),
+            stream("Movie.2019.1080p.WEB-DL.x264-B\n🇫🇷", "2 GB", 100),
+        ]);
+
+        assert_eq!(ranked[0].title, "Movie.2019.1080p.WEB-DL.x264-B\n🇫🇷");
+    }
+
+    #[test]
+    fn camera_recordings_rank_below_real_releases() {
+        let ranked = rank(&profile("best_quality"), vec![
+            stream("Movie.2019.1080p.HDCAM.x264-A", "2 GB", 900),
+            stream("Movie.2019.720p.WEBRip.x264-B", "1 GB", 10),
+        ]);
+
+        assert_eq!(ranked[0].title, "Movie.2019.720p.WEBRip.x264-B");
+    }
+
+    #[test]
+    fn parses_sizes() {
+        assert_eq!(parse_size_gb("5 GB"), Some(5.0));
+        assert_eq!(parse_size_gb("512 MB"), Some(0.5));
+        assert_eq!(parse_size_gb("1TB"), Some(1024.0));
+        assert_eq!(parse_size_gb("1073741824"), Some(1.0));
+        assert_eq!(parse_size_gb("big"), None);
+    }
+}
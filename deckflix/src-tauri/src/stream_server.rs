@@ -0,0 +1,300 @@
+use crate::error::DeckflixError;
+use librqbit::ManagedTorrent;
+use std::io::SeekFrom;
+use std::ops::Range;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncSeekExt, AsyncWriteExt, BufReader};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::task::{JoinHandle, JoinSet};
+
+// Requests with more header bytes than this are rejected
+const MAX_HEADER_BYTES: usize = 16 * 1024;
+
+const COPY_BUFFER_BYTES: usize = 256 * 1024;
+
+// Serves one file of a torrent over HTTP on 127.0.0.1 with Range support.
+// Every request opens its own torrent file stream at the requested offset, so
+// pieces are fetched around wherever the player reads and reads wait for them.
+// The offset of the latest byte sent is kept in `playhead`.
+pub struct StreamServer {
+    port: u16,
+    path: String,
+    // Accept loop; dropping it aborts every open connection too
+    task: JoinHandle<()>,
+}
+
+impl StreamServer {
+    // Listens on the first free port in `ports`
+    pub async fn start(
+        ports: Range<u16>,
+        handle: Arc<ManagedTorrent>,
+        file_index: usize,
+        file_name: &str,
+        playhead: Arc<AtomicU64>,
+    ) -> Result<Self, DeckflixError> {
+        let mut bound = None;
+        for port in ports.clone() {
+            if let Ok(listener) = TcpListener::bind(("127.0.0.1", port)).await {
+                bound = Some((listener, port));
+                break;
+            }
+        }
+        let (listener, port) = bound.ok_or_else(|| {
+            DeckflixError::torrent(format!("Failed to start stream server: ports {}-{} are all in use", ports.start, ports.end - 1))
+        })?;
+
+        // Players guess the container from the URL, so keep the file name in it
+        let path = format!("/{}", urlencoding::encode(file_name));
+        let content_type = content_type(file_name);
+
+        let task = tokio::spawn(async move {
+            let mut connections = JoinSet::new();
+            loop {
+                let (socket, _) = match listener.accept().await {
+                    Ok(conn) => conn,
+                    Err(e) => {
+                        println!("[RUST] [STREAM_SERVER] ❌ Accept failed: {}", e);
+                        break;
+                    }
+                };
+                while connections.try_join_next().is_some() {}
+
+                let handle = handle.clone();
+                let playhead = playhead.clone();
+                connections.spawn(async move {
+                    if let Err(e) = serve(socket, handle, file_index, content_type, &playhead).await {
+                        println!("[RUST] [STREAM_SERVER] ⚠️  Connection closed: {}", e);
+                    }
+                });
+            }
+        });
+
+        println!("[RUST] [STREAM_SERVER] ✅ Serving file #{} on 127.0.0.1:{}", file_index, port);
+        Ok(Self { port, path, task })
+    }
+
+    pub fn port(&self) -> u16 {
+        self.port
+    }
+
+    pub fn url(&self) -> String {
+        format!("http://127.0.0.1:{}{}", self.port, self.path)
+    }
+}
+
+impl Drop for StreamServer {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+// Handles one request and closes the connection. Players reconnect for every
+// seek anyway, so keep-alive isn't worth the bookkeeping.
+async fn serve(
+    socket: TcpStream,
+    handle: Arc<ManagedTorrent>,
+    file_index: usize,
+    content_type: &str,
+    playhead: &AtomicU64,
+) -> std::io::Result<()> {
+    let mut socket = BufReader::new(socket);
+
+    // Reads stop at MAX_HEADER_BYTES, even within a line that never ends
+    let mut head = String::new();
+    let mut limited = (&mut socket).take(MAX_HEADER_BYTES as u64);
+    let complete = loop {
+        let read = limited.read_line(&mut head).await?;
+        let complete = head.ends_with("\r\n\r\n") || head.ends_with("\n\n");
+        if read == 0 || complete {
+            break complete;
+        }
+    };
+    if !complete && limited.limit() == 0 {
+        return respond(&mut socket, "431 Request Header Fields Too Large", &[]).await;
+    }
+
+    if head.trim().is_empty() {
+        return Ok(());
+    }
+
+    let mut lines = head.lines();
+    let method = lines
+        .next()
+        .and_then(|line| line.split_whitespace().next())
+        .unwrap_or("")
+        .to_string();
+    let range = lines.find_map(|line| {
+        let (name, value) = line.split_once(':')?;
+        name.trim().eq_ignore_ascii_case("range").then(|| value.trim().to_string())
+    });
+
+    if method != "GET" && method != "HEAD" {
+        return respond(&mut socket, "405 Method Not Allowed", &[("Allow", "GET, HEAD".to_string())]).await;
+    }
+
+    let mut file_stream = match handle.stream(file_index) {
+        Ok(file_stream) => file_stream,
+        Err(e) => {
+            println!("[RUST] [STREAM_SERVER] ❌ Failed to open file #{}: {:#}", file_index, e);
+            return respond(&mut socket, "503 Service Unavailable", &[]).await;
+        }
+    };
+    let file_len = file_stream.len();
+
+    let (status, start, end) = match served_range(range.as_deref(), file_len) {
+        Ok(served) => served,
+        Err(content_range) => {
+            return respond(&mut socket, "416 Range Not Satisfiable", &[("Content-Range", content_range)]).await;
+        }
+    };
+    let length = if file_len == 0 { 0 } else { end - start + 1 };
+
+    let mut headers = vec![
+        ("Content-Type", content_type.to_string()),
+        ("Content-Length", length.to_string()),
+        ("Accept-Ranges", "bytes".to_string()),
+    ];
+    if status.starts_with("206") {
+        headers.push(("Content-Range", format!("bytes {}-{}/{}", start, end, file_len)));
+    }
+    respond(&mut socket, status, &headers).await?;
+
+    if method == "HEAD" || length == 0 {
+        return Ok(());
+    }
+
+    // Reads block until the pieces under the read position are downloaded
+    file_stream.seek(SeekFrom::Start(start)).await?;
+    let mut body = file_stream.take(length);
+    let mut buf = vec![0u8; COPY_BUFFER_BYTES];
+    let mut position = start;
+    loop {
+        let read = body.read(&mut buf).await?;
+        if read == 0 {
+            break;
+        }
+        socket.get_mut().write_all(&buf[..read]).await?;
+        position += read as u64;
+        playhead.store(position, Ordering::Relaxed);
+    }
+    socket.get_mut().flush().await
+}
+
+async fn respond(socket: &mut BufReader<TcpStream>, status: &str, headers: &[(&str, String)]) -> std::io::Result<()> {
+    let mut response = format!("HTTP/1.1 {}\r\nConnection: close\r\n", status);
+    for (name, value) in headers {
+        response.push_str(&format!("{}: {}\r\n", name, value));
+    }
+    if !headers.iter().any(|(name, _)| *name == "Content-Length") {
+        response.push_str("Content-Length: 0\r\n");
+    }
+    response.push_str("\r\n");
+    socket.get_mut().write_all(response.as_bytes()).await
+}
+
+// Status and inclusive byte range to answer a request with, or the Content-Range
+// of the 416 for a range that can't be served
+fn served_range(range: Option<&str>, file_len: u64) -> Result<(&'static str, u64, u64), String> {
+    match range.map(|range| parse_range(range, file_len)) {
+        None => Ok(("200 OK", 0, file_len.saturating_sub(1))),
+        Some(Some((start, end))) => Ok(("206 Partial Content", start, end)),
+        Some(None) => Err(format!("bytes */{}", file_len)),
+    }
+}
+
+// Inclusive byte range from a "bytes=start-end", "bytes=start-" or "bytes=-suffix"
+// header. Only the first range of a multi-range request is served.
+fn parse_range(header: &str, file_len: u64) -> Option<(u64, u64)> {
+    let spec = header.strip_prefix("bytes=")?.split(',').next()?.trim();
+    let (start, end) = spec.split_once('-')?;
+    let last = file_len.checked_sub(1)?;
+
+    let (start, end) = if start.is_empty() {
+        let suffix: u64 = end.parse().ok()?;
+        if suffix == 0 {
+            return None;
+        }
+        (file_len.saturating_sub(suffix), last)
+    } else {
+        let start: u64 = start.parse().ok()?;
+        let end = if end.is_empty() { last } else { end.parse::<u64>().ok()?.min(last) };
+        (start, end)
+    };
+
+    (start <= end).then_some((start, end))
+}
+
+fn content_type(file_name: &str) -> &'static str {
+    let extension = file_name.rsplit('.').next().unwrap_or("").to_lowercase();
+    match extension.as_str() {
+        "mp4" | "m4v" => "video/mp4",
+        "mkv" => "video/x-matroska",
+        "webm" => "video/webm",
+        "avi" => "video/x-msvideo",
+        "mov" => "video/quicktime",
+        "wmv" => "video/x-ms-wmv",
+        "flv" => "video/x-flv",
+        _ => "application/octet-stream",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn closed_and_open_ranges() {
+        assert_eq!(parse_range("bytes=0-99", 1000), Some((0, 99)));
+        assert_eq!(parse_range("bytes=500-", 1000), Some((500, 999)));
+        assert_eq!(parse_range("bytes=999-999", 1000), Some((999, 999)));
+        // An end past the file is cut to its last byte
+        assert_eq!(parse_range("bytes=900-5000", 1000), Some((900, 999)));
+    }
+
+    #[test]
+    fn suffix_ranges() {
+        assert_eq!(parse_range("bytes=-100", 1000), Some((900, 999)));
+        assert_eq!(parse_range("bytes=-5000", 1000), Some((0, 999)));
+        assert_eq!(parse_range("bytes=-0", 1000), None);
+    }
+
+    #[test]
+    fn unsatisfiable_ranges() {
+        assert_eq!(parse_range("bytes=100-50", 1000), None);
+        assert_eq!(parse_range("bytes=1000-", 1000), None);
+        assert_eq!(parse_range("bytes=2000-3000", 1000), None);
+        assert_eq!(parse_range("bytes=0-", 0), None);
+    }
+
+    #[test]
+    fn first_of_several_ranges() {
+        assert_eq!(parse_range("bytes=0-9, 20-29", 1000), Some((0, 9)));
+        assert_eq!(parse_range("bytes= 10-19,0-4", 1000), Some((10, 19)));
+    }
+
+    #[test]
+    fn malformed_ranges() {
+        for header in ["bytes=", "bytes=-", "bytes=abc-", "bytes=0-xyz", "bytes=10", "items=0-9", "0-9", "bytes=--5"] {
+            assert_eq!(parse_range(header, 1000), None, "{}", header);
+        }
+    }
+
+    #[test]
+    fn responses() {
+        assert_eq!(served_range(None, 1000), Ok(("200 OK", 0, 999)));
+        assert_eq!(served_range(Some("bytes=10-"), 1000), Ok(("206 Partial Content", 10, 999)));
+        assert_eq!(served_range(Some("bytes=1000-"), 1000), Err("bytes */1000".to_string()));
+        assert_eq!(served_range(Some("bytes=x-y"), 1000), Err("bytes */1000".to_string()));
+    }
+
+    #[test]
+    fn content_types() {
+        assert_eq!(content_type("Show.S01E01.mkv"), "video/x-matroska");
+        assert_eq!(content_type("MOVIE.MP4"), "video/mp4");
+        assert_eq!(content_type("clip.m4v"), "video/mp4");
+        assert_eq!(content_type("trailer.webm"), "video/webm");
+        assert_eq!(content_type("README"), "application/octet-stream");
+        assert_eq!(content_type("notes.txt"), "application/octet-stream");
+    }
+}
@@ -0,0 +1,305 @@
+use crate::error::DeckflixError;
+use crate::models::StreamStats;
+use crate::stream_server::StreamServer;
+use crate::stream_stats::StreamMonitor;
+use crate::torrent_cache::CacheLease;
+use librqbit::api::TorrentIdOrHash;
+use librqbit::{ManagedTorrent, Session};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::task::JoinHandle;
+
+// Local ports for stream servers; each session takes the first free one
+const STREAM_PORTS: Range<u16> = 8888..8908;
+
+// How often stream statistics are sent while streaming
+const STATS_INTERVAL: Duration = Duration::from_secs(1);
+
+// A live session on disk, as <records dir>/<app pid>-<id>.json, so one left
+// behind by a crash can be reaped on the next start
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct SessionRecord {
+    id: u64,
+    app_pid: u32,
+    info_hash: String,
+    port: Option<u16>,
+    player_pid: Option<u32>,
+}
+
+// A session that finished starting up
+#[derive(Debug, Clone)]
+pub struct StartedStream {
+    pub id: u64,
+    pub url: String,
+}
+
+// Serving the selected file to the player
+struct Playback {
+    monitor: Arc<StreamMonitor>,
+    server: StreamServer,
+    // Sends stream statistics every STATS_INTERVAL
+    stats_task: JoinHandle<()>,
+}
+
+// One torrent being streamed and everything started for it: the torrent in the
+// engine, the local HTTP server, the stats task and the player process. The
+// served file can change, e.g. to the next episode of a season pack, while the
+// torrent and its peers stay.
+// close() tears it all down; dropping an open session does the same without
+// waiting for the engine, so nothing outlives it on errors, cancelled startups
+// or panics.
+pub struct StreamSession {
+    engine: Arc<Session>,
+    handle: Arc<ManagedTorrent>,
+    record: SessionRecord,
+    record_path: PathBuf,
+    playback: Option<Playback>,
+    // Keeps the torrent's data in the cache while the session is open
+    _cache_lease: CacheLease,
+    closed: bool,
+}
+
+impl StreamSession {
+    // Takes ownership of a torrent just added to the engine
+    pub fn open(id: u64, records_dir: &Path, engine: Arc<Session>, handle: Arc<ManagedTorrent>, cache_lease: CacheLease) -> Self {
+        let record = SessionRecord {
+            id,
+            app_pid: std::process::id(),
+            info_hash: handle.info_hash().as_string(),
+            port: None,
+            player_pid: None,
+        };
+        let session = Self {
+            engine,
+            handle,
+            record_path: records_dir.join(format!("{}-{}.json", record.app_pid, id)),
+            record,
+            playback: None,
+            _cache_lease: cache_lease,
+            closed: false,
+        };
+        session.save_record();
+        println!("[RUST] [STREAM_SESSION] 🆕 Session {} opened for {}", id, session.record.info_hash);
+        session
+    }
+
+    pub fn id(&self) -> u64 {
+        self.record.id
+    }
+
+    pub fn handle(&self) -> &Arc<ManagedTorrent> {
+        &self.handle
+    }
+
+    pub fn info_hash(&self) -> &str {
+        &self.record.info_hash
+    }
+
+    // Starts serving one file of the torrent and sending its statistics to
+    // `on_stats`, after stopping the file served before and its player.
+    // Returns the URL for the player.
+    pub async fn serve(
+        &mut self,
+        file_index: usize,
+        file_name: &str,
+        relative_path: String,
+        on_stats: impl Fn(StreamStats) + Send + 'static,
+    ) -> Result<StartedStream, DeckflixError> {
+        self.stop_playback();
+        let monitor = Arc::new(StreamMonitor::new(self.engine.clone(), self.handle.clone(), file_index, relative_path)?);
+        let server = StreamServer::start(STREAM_PORTS, self.handle.clone(), file_index, file_name, monitor.playhead()).await?;
+
+        let stats_task = tokio::spawn({
+            let monitor = monitor.clone();
+            async move {
+                let mut ticker = tokio::time::interval(STATS_INTERVAL);
+                loop {
+                    ticker.tick().await;
+                    on_stats(monitor.stats());
+                }
+            }
+        });
+
+        let url = server.url();
+        self.record.port = Some(server.port());
+        self.save_record();
+        self.playback = Some(Playback { monitor, server, stats_task });
+
+        Ok(StartedStream { id: self.id(), url })
+    }
+
+    pub fn stats(&self) -> Option<StreamStats> {
+        self.playback.as_ref().map(|playback| playback.monitor.stats())
+    }
+
+    // The player reading this stream is stopped with the session
+    pub fn attach_player(&mut self, pid: u32) {
+        self.record.player_pid = Some(pid);
+        self.save_record();
+    }
+
+    pub async fn close(mut self) -> Result<(), DeckflixError> {
+        self.shutdown();
+        // Downloaded data is kept on disk
+        self.engine
+            .delete(TorrentIdOrHash::Id(self.handle.id()), false)
+            .await
+            .map_err(|e| DeckflixError::torrent(format!("Failed to stop torrent: {:#}", e)))?;
+        println!("[RUST] [STREAM_SESSION] ✅ Session {} closed", self.id());
+        Ok(())
+    }
+
+    // Stops serving the current file and its player; the torrent keeps running
+    fn stop_playback(&mut self) {
+        if let Some(playback) = self.playback.take() {
+            playback.stats_task.abort();
+            drop(playback.server);
+        }
+        if let Some(pid) = self.record.player_pid.take() {
+            println!("[RUST] [STREAM_SESSION] 🛑 Stopping player (PID: {})", pid);
+            kill_process(pid);
+        }
+        self.record.port = None;
+    }
+
+    // Everything but removing the torrent from the engine, which is async
+    fn shutdown(&mut self) {
+        self.closed = true;
+        self.stop_playback();
+        if let Err(e) = fs::remove_file(&self.record_path) {
+            println!("[RUST] [STREAM_SESSION] ⚠️  Failed to remove {}: {}", self.record_path.display(), e);
+        }
+    }
+
+    fn save_record(&self) {
+        let result = self
+            .record_path
+            .parent()
+            .map_or(Ok(()), fs::create_dir_all)
+            .and_then(|_| fs::write(&self.record_path, serde_json::to_string(&self.record).unwrap_or_default()));
+        if let Err(e) = result {
+            println!("[RUST] [STREAM_SESSION] ⚠️  Failed to write {}: {}", self.record_path.display(), e);
+        }
+    }
+}
+
+impl Drop for StreamSession {
+    fn drop(&mut self) {
+        if self.closed {
+            return;
+        }
+        println!("[RUST] [STREAM_SESSION] 🧹 Session {} dropped without closing - tearing down", self.id());
+        self.shutdown();
+
+        // Without a runtime the app is exiting and the engine goes with it
+        if let Ok(runtime) = tokio::runtime::Handle::try_current() {
+            let engine = self.engine.clone();
+            let id = self.handle.id();
+            runtime.spawn(async move {
+                if let Err(e) = engine.delete(TorrentIdOrHash::Id(id), false).await {
+                    println!("[RUST] [STREAM_SESSION] ⚠️  Failed to remove dropped torrent: {:#}", e);
+                }
+            });
+        }
+    }
+}
+
+// Cleans up after sessions whose app is no longer running: stops their players
+// and removes their records. Their downloaded data stays in the cache.
+pub fn reap_orphans(records_dir: &Path) {
+    let Ok(entries) = fs::read_dir(records_dir) else {
+        return;
+    };
+
+    for path in entries.flatten().map(|entry| entry.path()) {
+        if path.extension().and_then(|ext| ext.to_str()) != Some("json") {
+            continue;
+        }
+        let record = fs::read_to_string(&path)
+            .ok()
+            .and_then(|contents| serde_json::from_str::<SessionRecord>(&contents).ok());
+
+        if let Some(record) = &record {
+            // Another instance of the app still owns it
+            if record.app_pid == std::process::id() || process_running(record.app_pid) {
+                continue;
+            }
+            println!("[RUST] [STREAM_SESSION] 🧹 Reaping session {} of {} left by PID {}", record.id, record.info_hash, record.app_pid);
+            if let (Some(player_pid), Some(port)) = (record.player_pid, record.port) {
+                if is_player_of(player_pid, port) {
+                    println!("[RUST] [STREAM_SESSION] 🛑 Stopping orphaned player (PID: {})", player_pid);
+                    kill_process(player_pid);
+                }
+            }
+        } else {
+            println!("[RUST] [STREAM_SESSION] ⚠️  Removing unreadable session record {}", path.display());
+        }
+
+        if let Err(e) = fs::remove_file(&path) {
+            println!("[RUST] [STREAM_SESSION] ⚠️  Failed to remove {}: {}", path.display(), e);
+        }
+    }
+}
+
+fn process_running(pid: u32) -> bool {
+    command_line(pid).is_some()
+}
+
+// Whether `pid` is still a player reading from the stream server on `port`,
+// so a PID reused by an unrelated process is never killed
+fn is_player_of(pid: u32, port: u16) -> bool {
+    command_line(pid).is_some_and(|command| command.contains(&format!("127.0.0.1:{}", port)))
+}
+
+// The command line of a process, or None when no process has that PID
+#[cfg(target_os = "linux")]
+fn command_line(pid: u32) -> Option<String> {
+    fs::read(format!("/proc/{}/cmdline", pid))
+        .ok()
+        .map(|cmdline| String::from_utf8_lossy(&cmdline).replace('\0', " "))
+}
+
+// tasklist can't show command lines, so this asks WMI through PowerShell
+#[cfg(target_os = "windows")]
+fn command_line(pid: u32) -> Option<String> {
+    use std::os::windows::process::CommandExt;
+    const CREATE_NO_WINDOW: u32 = 0x0800_0000;
+
+    let script = format!(
+        "$p = Get-CimInstance Win32_Process -Filter 'ProcessId = {}'; if (-not $p) {{ exit 1 }}; $p.CommandLine",
+        pid
+    );
+    let output = std::process::Command::new("powershell")
+        .args(["-NoProfile", "-NonInteractive", "-Command", &script])
+        .creation_flags(CREATE_NO_WINDOW)
+        .output()
+        .ok()?;
+    output.status.success().then(|| String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "windows")))]
+fn command_line(pid: u32) -> Option<String> {
+    let output = std::process::Command::new("ps")
+        .args(["-p", &pid.to_string(), "-o", "command="])
+        .output()
+        .ok()?;
+    output.status.success().then(|| String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+fn kill_process(pid: u32) {
+    #[cfg(target_os = "windows")]
+    let result = std::process::Command::new("taskkill")
+        .args(["/f", "/pid", &pid.to_string()])
+        .output();
+
+    #[cfg(not(target_os = "windows"))]
+    let result = std::process::Command::new("kill").arg(pid.to_string()).output();
+
+    if let Err(e) = result {
+        println!("[RUST] [STREAM_SESSION] ⚠️  Failed to stop PID {}: {}", pid, e);
+    }
+}
@@ -0,0 +1,155 @@
+use crate::error::DeckflixError;
+use crate::models::StreamStats;
+use crate::torrent_streamer::{file_pieces, have_pieces};
+use librqbit::{ManagedTorrent, Session};
+use std::collections::VecDeque;
+use std::ops::Range;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+// Playhead positions from this far back are used to estimate the player's read rate;
+// no estimate is given until they span at least the minimum
+const READ_RATE_WINDOW: Duration = Duration::from_secs(60);
+const MIN_READ_RATE_WINDOW: Duration = Duration::from_secs(10);
+
+// Computes StreamStats for the torrent file being streamed. The stream server
+// writes the player's read position into `playhead`. Which pieces are there
+// comes from the engine's have-bitfield, so nothing is read to find out.
+pub struct StreamMonitor {
+    engine: Arc<Session>,
+    handle: Arc<ManagedTorrent>,
+    file_index: usize,
+    file_name: String,
+    file_size: u64,
+    file_offset: u64, // where the file starts in the torrent
+    piece_length: u64,
+    pieces: Range<usize>, // the pieces the file spans
+    playhead: Arc<AtomicU64>,
+    playhead_samples: Mutex<VecDeque<(Instant, u64)>>,
+}
+
+impl StreamMonitor {
+    pub fn new(engine: Arc<Session>, handle: Arc<ManagedTorrent>, file_index: usize, file_name: String) -> Result<Self, DeckflixError> {
+        let (piece_length, file) = handle
+            .with_metadata(|metadata| {
+                let file = metadata
+                    .file_infos
+                    .get(file_index)
+                    .map(|f| (f.offset_in_torrent, f.len));
+                (metadata.info.piece_length as u64, file)
+            })
+            .map_err(|e| DeckflixError::torrent(format!("Torrent metadata unavailable: {:#}", e)))?;
+        let (file_offset, file_size) = file.ok_or_else(|| DeckflixError::torrent(format!("Torrent has no file #{}", file_index)))?;
+        let pieces = file_pieces(&handle, file_index)?;
+
+        Ok(Self {
+            engine,
+            handle,
+            file_index,
+            file_name,
+            file_size,
+            file_offset,
+            piece_length: piece_length.max(1),
+            pieces,
+            playhead: Arc::new(AtomicU64::new(0)),
+            playhead_samples: Mutex::new(VecDeque::new()),
+        })
+    }
+
+    pub fn playhead(&self) -> Arc<AtomicU64> {
+        self.playhead.clone()
+    }
+
+    pub fn stats(&self) -> StreamStats {
+        let stats = self.handle.stats();
+        let live = stats.live.as_ref();
+
+        let downloaded_bytes = stats.file_progress.get(self.file_index).copied().unwrap_or(0);
+        let remaining_bytes = self.file_size.saturating_sub(downloaded_bytes);
+        let download_speed = live.map(|l| mib_to_bytes(l.download_speed.mbps)).unwrap_or(0);
+
+        let haves = have_pieces(&self.engine, &self.handle);
+        let playhead = self.playhead.load(Ordering::Relaxed);
+        let buffered_ahead_bytes = self.buffered_ahead(playhead, haves.as_deref());
+
+        StreamStats {
+            info_hash: self.handle.info_hash().as_string(),
+            name: self.handle.name(),
+            file_index: self.file_index,
+            file_name: self.file_name.clone(),
+            file_size: self.file_size,
+            downloaded_bytes,
+            progress: if self.file_size > 0 { downloaded_bytes as f64 / self.file_size as f64 } else { 1.0 },
+            download_speed,
+            upload_speed: live.map(|l| mib_to_bytes(l.upload_speed.mbps)).unwrap_or(0),
+            peers: live.map(|l| l.snapshot.peer_stats.live).unwrap_or(0),
+            pieces_verified: self.pieces_verified(haves.as_deref()),
+            pieces_total: self.pieces.len() as u64,
+            playhead_bytes: playhead,
+            buffered_ahead_bytes,
+            buffered_ahead_secs: self.read_rate(playhead).map(|rate| buffered_ahead_bytes as f64 / rate),
+            eta_secs: match (remaining_bytes, download_speed) {
+                (0, _) => Some(0),
+                (_, 0) => None,
+                (remaining, speed) => Some(remaining / speed),
+            },
+            finished: remaining_bytes == 0,
+        }
+    }
+
+    // Verified pieces among the ones the file spans
+    fn pieces_verified(&self, haves: Option<&[bool]>) -> u64 {
+        haves
+            .and_then(|haves| haves.get(self.pieces.clone()))
+            .map(|pieces| pieces.iter().filter(|have| **have).count() as u64)
+            .unwrap_or(0)
+    }
+
+    // Bytes downloaded without a gap from `from`: the verified pieces in a row
+    // starting with the one under it
+    fn buffered_ahead(&self, from: u64, haves: Option<&[bool]>) -> u64 {
+        let Some(haves) = haves else {
+            return 0;
+        };
+        if from >= self.file_size {
+            return 0;
+        }
+
+        let first = ((self.file_offset + from) / self.piece_length) as usize;
+        let verified = haves
+            .get(first..self.pieces.end)
+            .map(|pieces| pieces.iter().take_while(|have| **have).count() as u64)
+            .unwrap_or(0);
+        let end = ((first as u64 + verified) * self.piece_length)
+            .saturating_sub(self.file_offset)
+            .min(self.file_size);
+        end.saturating_sub(from)
+    }
+
+    // Bytes per second the player has been reading, once there's enough history
+    fn read_rate(&self, playhead: u64) -> Option<f64> {
+        let now = Instant::now();
+        let mut samples = self.playhead_samples.lock().unwrap();
+
+        // Seeking back starts a new estimate
+        if samples.back().is_some_and(|(_, position)| playhead < *position) {
+            samples.clear();
+        }
+        samples.push_back((now, playhead));
+        while samples.front().is_some_and(|(at, _)| now.duration_since(*at) > READ_RATE_WINDOW) {
+            samples.pop_front();
+        }
+
+        let (first_at, first_position) = *samples.front()?;
+        let elapsed = now.duration_since(first_at);
+        if elapsed < MIN_READ_RATE_WINDOW || playhead <= first_position {
+            return None;
+        }
+        Some((playhead - first_position) as f64 / elapsed.as_secs_f64())
+    }
+}
+
+fn mib_to_bytes(mib_per_sec: f64) -> u64 {
+    (mib_per_sec * 1024.0 * 1024.0) as u64
+}
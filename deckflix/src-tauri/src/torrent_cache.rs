@@ -0,0 +1,494 @@
+use crate::error::DeckflixError;
+use crate::models::{TorrentCacheEntry, TorrentCacheSettings, TorrentCacheStatus};
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const SETTINGS_FILE: &str = "torrent_cache.json";
+
+// Last use of every torrent, kept in the cache directory
+const INDEX_FILE: &str = "index.json";
+
+const DEFAULT_MAX_SIZE_BYTES: u64 = 10 * 1024 * 1024 * 1024;
+
+struct CacheState {
+    settings: TorrentCacheSettings,
+    // Leases per info hash
+    in_use: HashMap<String, usize>,
+    // Last use of every torrent in the current directory, as saved in its INDEX_FILE
+    last_used: BTreeMap<String, u64>,
+}
+
+// Torrent data on disk, one <info hash> directory per torrent. When a new
+// download needs room, the least recently used torrents are deleted until the
+// cache fits in its maximum size again; torrents that are leased (streaming or
+// downloading) are never deleted. Only directories named like an info hash are
+// ever touched, so the cache can point at a shared folder such as an SD card.
+// Scanning and deleting run on blocking threads, and the state lock is never
+// held across file system calls, so a large cache can't stall the runtime.
+pub struct TorrentCache {
+    default_dir: PathBuf,
+    settings_path: PathBuf,
+    state: Mutex<CacheState>,
+    // Held while INDEX_FILE is written so writes land in order
+    index_writes: Mutex<()>,
+}
+
+// Keeps one torrent's data out of eviction until dropped
+pub struct CacheLease {
+    cache: Arc<TorrentCache>,
+    info_hash: String,
+}
+
+impl Drop for CacheLease {
+    fn drop(&mut self) {
+        self.cache.release(&self.info_hash);
+    }
+}
+
+impl TorrentCache {
+    pub fn new(data_dir: &Path, default_dir: PathBuf) -> Self {
+        let settings_path = data_dir.join(SETTINGS_FILE);
+        let settings = match fs::read_to_string(&settings_path) {
+            Ok(contents) => serde_json::from_str(&contents).unwrap_or_else(|e| {
+                println!("[RUST] [TORRENT_CACHE] ERROR: Failed to parse {}: {}, using defaults", settings_path.display(), e);
+                default_settings()
+            }),
+            Err(_) => default_settings(),
+        };
+
+        let cache = Self {
+            default_dir,
+            settings_path,
+            state: Mutex::new(CacheState {
+                settings,
+                in_use: HashMap::new(),
+                last_used: BTreeMap::new(),
+            }),
+            index_writes: Mutex::new(()),
+        };
+        cache.state.lock().unwrap().last_used = read_index(&cache.dir());
+        println!("[RUST] [TORRENT_CACHE] Using {} (max {} bytes)", cache.dir().display(), cache.settings().max_size_bytes);
+        cache
+    }
+
+    pub fn settings(&self) -> TorrentCacheSettings {
+        self.state.lock().unwrap().settings.clone()
+    }
+
+    pub fn dir(&self) -> PathBuf {
+        self.dir_for(&self.state.lock().unwrap().settings)
+    }
+
+    fn dir_for(&self, settings: &TorrentCacheSettings) -> PathBuf {
+        settings
+            .directory
+            .as_ref()
+            .map(PathBuf::from)
+            .unwrap_or_else(|| self.default_dir.clone())
+    }
+
+    pub fn torrent_dir(&self, info_hash: &str) -> PathBuf {
+        self.dir().join(info_hash.to_lowercase())
+    }
+
+    // Runs file system work on a blocking thread instead of a runtime worker
+    async fn blocking<T: Send + 'static>(
+        self: &Arc<Self>,
+        work: impl FnOnce(&Arc<TorrentCache>) -> T + Send + 'static,
+    ) -> Result<T, DeckflixError> {
+        let cache = self.clone();
+        tokio::task::spawn_blocking(move || work(&cache))
+            .await
+            .map_err(|e| DeckflixError::storage(format!("Torrent cache task failed: {}", e)))
+    }
+
+    // Torrents already in the old directory stay there and are no longer managed
+    pub async fn set_settings(self: &Arc<Self>, settings: TorrentCacheSettings) -> Result<TorrentCacheStatus, DeckflixError> {
+        if settings.max_size_bytes == 0 {
+            return Err(DeckflixError::invalid_request("The torrent cache size must be greater than zero"));
+        }
+        self.blocking(move |cache| cache.apply_settings(settings)).await?
+    }
+
+    fn apply_settings(self: &Arc<Self>, settings: TorrentCacheSettings) -> Result<TorrentCacheStatus, DeckflixError> {
+        let dir = self.dir_for(&settings);
+        fs::create_dir_all(&dir)
+            .map_err(|e| DeckflixError::storage(format!("Failed to create {}: {}", dir.display(), e)))?;
+
+        let json = serde_json::to_string_pretty(&settings)?;
+        if let Some(parent) = self.settings_path.parent() {
+            fs::create_dir_all(parent)
+                .map_err(|e| DeckflixError::storage(format!("Failed to create {}: {}", parent.display(), e)))?;
+        }
+        fs::write(&self.settings_path, json)
+            .map_err(|e| DeckflixError::storage(format!("Failed to write {}: {}", self.settings_path.display(), e)))?;
+
+        println!("[RUST] [TORRENT_CACHE] Now using {} (max {} bytes)", dir.display(), settings.max_size_bytes);
+        let index = read_index(&dir);
+        {
+            let mut state = self.state.lock().unwrap();
+            if self.dir_for(&state.settings) != dir {
+                state.last_used = index;
+            }
+            state.settings = settings;
+        }
+
+        // A smaller limit applies right away
+        self.evict(0);
+        Ok(self.scan())
+    }
+
+    pub fn lease(self: &Arc<Self>, info_hash: &str) -> CacheLease {
+        let info_hash = info_hash.to_lowercase();
+        {
+            let mut state = self.state.lock().unwrap();
+            *state.in_use.entry(info_hash.clone()).or_default() += 1;
+            state.last_used.insert(info_hash.clone(), now_secs());
+        }
+        self.save_index();
+
+        CacheLease {
+            cache: self.clone(),
+            info_hash,
+        }
+    }
+
+    fn release(self: &Arc<Self>, info_hash: &str) {
+        {
+            let mut state = self.state.lock().unwrap();
+            if let Some(count) = state.in_use.get_mut(info_hash) {
+                *count -= 1;
+                if *count == 0 {
+                    state.in_use.remove(info_hash);
+                }
+            }
+            // The torrent was used until now
+            state.last_used.insert(info_hash.to_string(), now_secs());
+        }
+        self.save_index();
+    }
+
+    fn is_leased(&self, info_hash: &str) -> bool {
+        self.state.lock().unwrap().in_use.contains_key(info_hash)
+    }
+
+    // Writes the index on a blocking thread when called from the runtime
+    fn save_index(self: &Arc<Self>) {
+        let cache = self.clone();
+        match tokio::runtime::Handle::try_current() {
+            Ok(runtime) => {
+                runtime.spawn_blocking(move || cache.write_index());
+            }
+            Err(_) => cache.write_index(),
+        }
+    }
+
+    fn write_index(&self) {
+        let _writing = self.index_writes.lock().unwrap();
+        // Read after the previous write finished, so the newest index is written last
+        let (dir, index) = {
+            let state = self.state.lock().unwrap();
+            (self.dir_for(&state.settings), state.last_used.clone())
+        };
+        if let Err(e) = write_index(&dir, &index) {
+            println!("[RUST] [TORRENT_CACHE] ⚠️  {}", e);
+        }
+    }
+
+    pub async fn status(self: &Arc<Self>) -> Result<TorrentCacheStatus, DeckflixError> {
+        self.blocking(|cache| cache.scan()).await
+    }
+
+    fn scan(&self) -> TorrentCacheStatus {
+        let (dir, max_size_bytes) = {
+            let state = self.state.lock().unwrap();
+            (self.dir_for(&state.settings), state.settings.max_size_bytes)
+        };
+        let entries = self.entries(&dir);
+
+        TorrentCacheStatus {
+            directory: dir.to_string_lossy().into_owned(),
+            max_size_bytes,
+            used_bytes: entries.iter().map(|entry| entry.size_bytes).sum(),
+            entries,
+        }
+    }
+
+    // Every torrent in `dir`, least recently used first
+    fn entries(&self, dir: &Path) -> Vec<TorrentCacheEntry> {
+        let (in_use, last_used) = {
+            let state = self.state.lock().unwrap();
+            (state.in_use.clone(), state.last_used.clone())
+        };
+        cache_entries(dir, &in_use, &last_used)
+    }
+
+    // Evicts the least recently used torrents until `needed_bytes` more fit
+    pub async fn make_room(self: &Arc<Self>, needed_bytes: u64) {
+        if let Err(e) = self.blocking(move |cache| cache.evict(needed_bytes)).await {
+            println!("[RUST] [TORRENT_CACHE] ⚠️  {}", e);
+        }
+    }
+
+    fn evict(&self, needed_bytes: u64) {
+        let (dir, max_size) = {
+            let state = self.state.lock().unwrap();
+            (self.dir_for(&state.settings), state.settings.max_size_bytes)
+        };
+        let entries = self.entries(&dir);
+        let mut used: u64 = entries.iter().map(|entry| entry.size_bytes).sum();
+
+        for entry in entries.iter().filter(|entry| !entry.in_use) {
+            if used.saturating_add(needed_bytes) <= max_size {
+                break;
+            }
+            // Leased since the scan
+            if self.is_leased(&entry.info_hash) {
+                continue;
+            }
+            println!("[RUST] [TORRENT_CACHE] 🧹 Evicting {} ({} bytes)", entry.info_hash, entry.size_bytes);
+            match fs::remove_dir_all(dir.join(&entry.info_hash)) {
+                Ok(()) => used -= entry.size_bytes,
+                Err(e) => println!("[RUST] [TORRENT_CACHE] ⚠️  Failed to evict {}: {}", entry.info_hash, e),
+            }
+        }
+
+        if used.saturating_add(needed_bytes) > max_size {
+            println!("[RUST] [TORRENT_CACHE] ⚠️  Cache will exceed its limit: {} bytes used, {} more needed, {} max",
+                    used, needed_bytes, max_size);
+        }
+        self.prune_index(&dir);
+    }
+
+    // Deletes one torrent's data, or every torrent not in use
+    pub async fn clear(self: &Arc<Self>, info_hash: Option<String>) -> Result<TorrentCacheStatus, DeckflixError> {
+        self.blocking(move |cache| cache.remove(info_hash.as_deref())).await?
+    }
+
+    fn remove(&self, info_hash: Option<&str>) -> Result<TorrentCacheStatus, DeckflixError> {
+        let dir = self.dir();
+        let entries = self.entries(&dir);
+
+        let targets: Vec<&TorrentCacheEntry> = match info_hash.map(|hash| hash.to_lowercase()) {
+            Some(hash) => {
+                let entry = entries
+                    .iter()
+                    .find(|entry| entry.info_hash == hash)
+                    .ok_or_else(|| DeckflixError::not_found(format!("Torrent not in cache: {}", hash)))?;
+                if entry.in_use {
+                    return Err(DeckflixError::invalid_request(format!("Torrent is in use: {}", hash)));
+                }
+                vec![entry]
+            }
+            None => entries.iter().filter(|entry| !entry.in_use).collect(),
+        };
+
+        for entry in targets {
+            // Leased since the scan
+            if self.is_leased(&entry.info_hash) {
+                continue;
+            }
+            println!("[RUST] [TORRENT_CACHE] 🗑️  Removing {} ({} bytes)", entry.info_hash, entry.size_bytes);
+            fs::remove_dir_all(dir.join(&entry.info_hash))
+                .map_err(|e| DeckflixError::storage(format!("Failed to remove {}: {}", entry.info_hash, e)))?;
+        }
+
+        if info_hash.is_none() {
+            remove_legacy_cache();
+        }
+        self.prune_index(&dir);
+
+        Ok(self.scan())
+    }
+
+    // Forgets torrents whose data is gone
+    fn prune_index(&self, dir: &Path) {
+        let known: Vec<String> = self.state.lock().unwrap().last_used.keys().cloned().collect();
+        let gone: Vec<String> = known.into_iter().filter(|info_hash| !dir.join(info_hash).is_dir()).collect();
+        if gone.is_empty() {
+            return;
+        }
+        {
+            let mut state = self.state.lock().unwrap();
+            for info_hash in &gone {
+                // A torrent leased meanwhile is about to be downloaded again
+                if !state.in_use.contains_key(info_hash) {
+                    state.last_used.remove(info_hash);
+                }
+            }
+        }
+        self.write_index();
+    }
+}
+
+fn default_settings() -> TorrentCacheSettings {
+    TorrentCacheSettings {
+        directory: None,
+        max_size_bytes: DEFAULT_MAX_SIZE_BYTES,
+    }
+}
+
+// Every torrent in `dir`, least recently used first
+fn cache_entries(dir: &Path, in_use: &HashMap<String, usize>, index: &BTreeMap<String, u64>) -> Vec<TorrentCacheEntry> {
+    let Ok(read_dir) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+
+    let mut entries: Vec<TorrentCacheEntry> = read_dir
+        .flatten()
+        .filter(|entry| entry.path().is_dir())
+        .filter_map(|entry| {
+            let info_hash = entry.file_name().to_str()?.to_string();
+            if !is_info_hash(&info_hash) {
+                return None;
+            }
+            // Torrents from before the index existed count as used when last written
+            let last_used = index.get(&info_hash).copied().unwrap_or_else(|| {
+                entry
+                    .metadata()
+                    .and_then(|metadata| metadata.modified())
+                    .ok()
+                    .and_then(|modified| modified.duration_since(UNIX_EPOCH).ok())
+                    .map(|age| age.as_secs())
+                    .unwrap_or(0)
+            });
+
+            Some(TorrentCacheEntry {
+                size_bytes: disk_usage(&entry.path()),
+                last_used,
+                in_use: in_use.contains_key(&info_hash),
+                info_hash,
+            })
+        })
+        .collect();
+
+    entries.sort_by_key(|entry| entry.last_used);
+    entries
+}
+
+// Only directories named by the normalized hash belong to the cache
+fn is_info_hash(name: &str) -> bool {
+    normalize_info_hash(name).as_deref() == Some(name)
+}
+
+// A BitTorrent v1 info hash as lowercase hex, the one form used for session,
+// download and cache lookups. Magnet links and addons send either 40 hex digits
+// or 32 base32 characters (RFC 4648, in either case); anything else is None.
+pub fn normalize_info_hash(info_hash: &str) -> Option<String> {
+    match info_hash.len() {
+        40 if info_hash.chars().all(|c| c.is_ascii_hexdigit()) => Some(info_hash.to_ascii_lowercase()),
+        32 => {
+            let mut hex = String::with_capacity(40);
+            let mut bits: u64 = 0;
+            let mut bit_count = 0;
+            for c in info_hash.chars() {
+                let value = match c.to_ascii_uppercase() {
+                    c @ 'A'..='Z' => c as u64 - 'A' as u64,
+                    c @ '2'..='7' => c as u64 - '2' as u64 + 26,
+                    _ => return None,
+                };
+                bits = (bits << 5) | value;
+                bit_count += 5;
+                if bit_count >= 8 {
+                    bit_count -= 8;
+                    hex.push_str(&format!("{:02x}", (bits >> bit_count) & 0xff));
+                }
+            }
+            Some(hex)
+        }
+        _ => None,
+    }
+}
+
+fn read_index(dir: &Path) -> BTreeMap<String, u64> {
+    fs::read_to_string(dir.join(INDEX_FILE))
+        .ok()
+        .and_then(|contents| serde_json::from_str(&contents).ok())
+        .unwrap_or_default()
+}
+
+fn write_index(dir: &Path, index: &BTreeMap<String, u64>) -> Result<(), String> {
+    let path = dir.join(INDEX_FILE);
+    fs::create_dir_all(dir)
+        .and_then(|_| fs::write(&path, serde_json::to_string(index).unwrap_or_default()))
+        .map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+}
+
+// Torrent files are sparse until downloaded, so count allocated space where the OS reports it
+fn disk_usage(path: &Path) -> u64 {
+    let Ok(metadata) = fs::symlink_metadata(path) else {
+        return 0;
+    };
+    if metadata.is_dir() {
+        fs::read_dir(path)
+            .map(|entries| entries.flatten().map(|entry| disk_usage(&entry.path())).sum())
+            .unwrap_or(0)
+    } else {
+        allocated_bytes(&metadata)
+    }
+}
+
+#[cfg(unix)]
+fn allocated_bytes(metadata: &fs::Metadata) -> u64 {
+    use std::os::unix::fs::MetadataExt;
+    metadata.blocks() * 512
+}
+
+#[cfg(not(unix))]
+fn allocated_bytes(metadata: &fs::Metadata) -> u64 {
+    metadata.len()
+}
+
+// Peerflix, used before the built-in engine, downloaded here and never cleaned up
+fn remove_legacy_cache() {
+    let legacy_dir = std::env::temp_dir().join("torrent-stream");
+    if legacy_dir.is_dir() {
+        println!("[RUST] [TORRENT_CACHE] 🗑️  Removing old Peerflix downloads in {}", legacy_dir.display());
+        if let Err(e) = fs::remove_dir_all(&legacy_dir) {
+            println!("[RUST] [TORRENT_CACHE] ⚠️  Failed to remove {}: {}", legacy_dir.display(), e);
+        }
+    }
+}
+
+fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HEX: &str = "f2a128331553da4fe580079977b980578ed08071";
+
+    #[test]
+    fn normalizes_hex_and_base32_to_lowercase_hex() {
+        assert_eq!(normalize_info_hash(HEX).as_deref(), Some(HEX));
+        assert_eq!(normalize_info_hash(&HEX.to_uppercase()).as_deref(), Some(HEX));
+        assert_eq!(normalize_info_hash("6KQSQMYVKPNE7ZMAA6MXPOMAK6HNBADR").as_deref(), Some(HEX));
+        assert_eq!(normalize_info_hash("6kqsqmyvkpne7zmaa6mxpomak6hnbadr").as_deref(), Some(HEX));
+    }
+
+    #[test]
+    fn rejects_other_hashes() {
+        assert_eq!(normalize_info_hash(""), None);
+        assert_eq!(normalize_info_hash(&HEX[1..]), None);
+        assert_eq!(normalize_info_hash("g2a128331553da4fe580079977b980578ed08071"), None);
+        // 0, 1 and 8 are not in the base32 alphabet
+        assert_eq!(normalize_info_hash("0KQSQMYVKPNE7ZMAA6MXPOMAK6HNBADR"), None);
+        assert_eq!(normalize_info_hash("6KQSQMYVKPNE7ZMAA6MXPOMAK6HNBAD="), None);
+    }
+
+    #[test]
+    fn only_normalized_names_are_cache_entries() {
+        assert!(is_info_hash(HEX));
+        assert!(!is_info_hash(&HEX.to_uppercase()));
+        assert!(!is_info_hash("6KQSQMYVKPNE7ZMAA6MXPOMAK6HNBADR"));
+        assert!(!is_info_hash("index.json"));
+    }
+}
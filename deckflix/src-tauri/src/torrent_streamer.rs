@@ -1,210 +1,756 @@
-use std::process::{Child, Command};
+use crate::error::DeckflixError;
+use crate::models::{EpisodeRef, StartupFailure, StartupState, StreamStats, TorrentFile, TorrentFileList, TorrentStartup};
+use crate::release_parser;
+use crate::stream_session::{self, StartedStream, StreamSession};
+use crate::torrent_cache::{normalize_info_hash, TorrentCache};
+use librqbit::api::TorrentIdOrHash;
+use librqbit::Api;
+use librqbit::dht::PersistentDhtConfig;
+use librqbit::{AddTorrent, AddTorrentOptions, AddTorrentResponse, ManagedTorrent, Session, SessionOptions};
+use std::collections::HashSet;
+use std::io::SeekFrom;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
-use tokio::sync::Mutex;
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncReadExt, AsyncSeekExt};
+use tokio::sync::{watch, Mutex, OnceCell};
 
+// Incoming peer connections; the first free port in the range is used
+const LISTEN_PORTS: std::ops::Range<u16> = 6881..6891;
+
+// How long to look for peers that can send the torrent's metadata
+const METADATA_TIMEOUT: Duration = Duration::from_secs(60);
+
+// How long to wait for a peer connection once the metadata is known
+const PEER_TIMEOUT: Duration = Duration::from_secs(30);
+
+// Buffering fails when no new data arrives for this long
+const BUFFER_STALL_TIMEOUT: Duration = Duration::from_secs(30);
+
+// Downloaded before the player starts: the start of the file, plus its end
+// where many containers keep their index
+const HEAD_BUFFER_BYTES: u64 = 8 * 1024 * 1024;
+const TAIL_BUFFER_BYTES: u64 = 1024 * 1024;
+
+// How often startup progress is checked and reported
+const STARTUP_TICK: Duration = Duration::from_millis(500);
+
+const VIDEO_EXTENSIONS: [&str; 8] = ["mp4", "mkv", "avi", "mov", "wmv", "flv", "webm", "m4v"];
+
+// Magnet link for an info hash with the trackers from Stremio `sources`
+// ("tracker:<url>"; "dht:" entries need nothing in the link). Without any
+// tracker, peers are found through the DHT only.
+pub fn magnet_link(info_hash: &str, sources: &[String]) -> String {
+    let trackers = sources.iter().filter_map(|s| s.strip_prefix("tracker:"));
+
+    let mut magnet = format!("magnet:?xt=urn:btih:{}", info_hash);
+    for tracker in trackers {
+        magnet.push_str("&tr=");
+        magnet.push_str(&urlencoding::encode(tracker));
+    }
+    magnet
+}
+
+// Info hash and tracker sources of a magnet link
+pub fn parse_magnet(magnet: &str) -> Option<(String, Vec<String>)> {
+    let query = magnet.strip_prefix("magnet:?")?;
+    let mut info_hash = None;
+    let mut sources = Vec::new();
+
+    for param in query.split('&') {
+        if let Some(hash) = param.strip_prefix("xt=urn:btih:") {
+            info_hash = Some(normalize_info_hash(hash)?);
+        } else if let Some(tracker) = param.strip_prefix("tr=") {
+            let tracker = urlencoding::decode(tracker).map(|t| t.into_owned()).unwrap_or_else(|_| tracker.to_string());
+            sources.push(format!("tracker:{}", tracker));
+        }
+    }
+
+    info_hash.map(|hash| (hash, sources))
+}
+
+// Which file of a torrent to play, as passed to select_file
+#[derive(Debug, Clone, Copy)]
+struct FileRequest {
+    file_idx: Option<u32>,
+    episode: Option<EpisodeRef>,
+}
+
+// In-process BitTorrent engine. The session (DHT, listener) is started on the
+// first stream and reused; only one torrent is streamed at a time, as a
+// StreamSession. Starting another file of the streamed torrent (the next
+// episode of a season pack) switches files in that session instead. Methods
+// take &self so stats and stop requests are never held up by a stream that is
+// still starting. Torrent data goes to the cache; the engine's own state
+// (session records, DHT table) stays in `state_dir`, which must be outside the
+// cache so eviction never deletes it.
 pub struct TorrentStreamer {
-    peerflix_process: Arc<Mutex<Option<Child>>>,
-    stream_url: Arc<Mutex<Option<String>>>,
-    stream_port: u16,
+    state_dir: PathBuf,
+    records_dir: PathBuf,
+    cache: Arc<TorrentCache>,
+    session: OnceCell<Arc<Session>>,
+    active: Mutex<Option<StreamSession>>,
+    // Held while a stream starts so startups don't interleave
+    startup: Mutex<()>,
+    next_id: AtomicU64,
+    // Sessions with a lower id than this were stopped; cancels their startup
+    stopped_before: watch::Sender<u64>,
 }
 
 impl TorrentStreamer {
-    pub fn new() -> Self {
+    pub fn new(state_dir: PathBuf, cache: Arc<TorrentCache>) -> Self {
+        let records_dir = state_dir.join("sessions");
+        stream_session::reap_orphans(&records_dir);
+
         Self {
-            peerflix_process: Arc::new(Mutex::new(None)),
-            stream_url: Arc::new(Mutex::new(None)),
-            stream_port: 8888,
+            state_dir,
+            records_dir,
+            cache,
+            session: OnceCell::new(),
+            active: Mutex::new(None),
+            startup: Mutex::new(()),
+            next_id: AtomicU64::new(0),
+            stopped_before: watch::Sender::new(0),
         }
     }
 
-    pub async fn start_stream(&self, magnet_link: String) -> Result<String, String> {
+    async fn session(&self) -> Result<&Arc<Session>, DeckflixError> {
+        self.session
+            .get_or_try_init(|| async {
+                println!("[RUST] [TORRENT] 🚀 Starting torrent session in {}", self.state_dir.display());
+                let options = SessionOptions {
+                    listen_port_range: Some(LISTEN_PORTS),
+                    enable_upnp_port_forwarding: false,
+                    // Keep the DHT routing table between runs so peers are found faster
+                    dht_config: Some(PersistentDhtConfig {
+                        config_filename: Some(self.state_dir.join("dht.json")),
+                        ..Default::default()
+                    }),
+                    ..Default::default()
+                };
+                // Every torrent is added with its own folder in the cache, so this
+                // default output folder is never written to
+                Session::new_with_opts(self.cache.dir(), options)
+                    .await
+                    .map_err(|e| DeckflixError::torrent(format!("Failed to start torrent session: {:#}", e)))
+            })
+            .await
+    }
+
+    // Resolves the torrent's metadata, selects one file, buffers its start and
+    // serves it over local HTTP. The file is chosen by select_file from `file_idx`,
+    // the 0-based index from the addon (or picked by the user), and `episode`. Each startup state is passed
+    // to `on_state`, ending with Ready or Failed; after that `on_stats` gets the
+    // stream statistics every second until the stream stops. Any current stream
+    // of another torrent, or one still starting, is stopped first.
+    pub async fn start_stream(
+        &self,
+        info_hash: &str,
+        file_idx: Option<u32>,
+        episode: Option<EpisodeRef>,
+        sources: &[String],
+        mut on_state: impl FnMut(TorrentStartup),
+        on_stats: impl Fn(StreamStats) + Send + 'static,
+    ) -> Result<StartedStream, DeckflixError> {
+        let infohash = normalize_info_hash(info_hash)
+            .ok_or_else(|| DeckflixError::invalid_request(format!("Invalid infohash: {}", info_hash)))?;
+
         println!("[RUST] [TORRENT] ================================================");
-        println!("[RUST] [TORRENT] Starting Peerflix torrent streaming");
-        println!("[RUST] [TORRENT] Magnet: {}", magnet_link);
+        println!("[RUST] [TORRENT] Starting torrent stream");
+        println!("[RUST] [TORRENT] 🔑 Infohash: {} | File index: {:?} | Episode: {:?} | Sources: {}", infohash, file_idx, episode, sources.len());
         println!("[RUST] [TORRENT] ================================================");
 
-        // Stop any existing stream first
+        // A stream of the same torrent keeps its metadata, peers and downloaded
+        // pieces; any other stream is stopped first
+        let reused = {
+            let mut active = self.active.lock().await;
+            match active.as_ref() {
+                Some(session) if session.info_hash() == infohash => active.take(),
+                _ => None,
+            }
+        };
         self.stop_stream().await?;
-
-        // Extract infohash from magnet link
-        let infohash = if let Some(start) = magnet_link.find("btih:") {
-            let hash_start = start + 5;
-            let hash_end = magnet_link[hash_start..].find('&').map(|p| hash_start + p).unwrap_or(magnet_link.len());
-            magnet_link[hash_start..hash_end].to_string()
-        } else {
-            return Err("Invalid magnet link: no infohash found".to_string());
+        let _startup = self.startup.lock().await;
+        let id = self.next_id.fetch_add(1, Ordering::SeqCst);
+
+        let mut report = |state: StartupState| {
+            on_state(TorrentStartup {
+                info_hash: infohash.clone(),
+                state,
+            })
         };
 
-        println!("[RUST] [TORRENT] 🔑 Extracted infohash: {}", infohash);
-
-        // Check if peerflix is installed - cross-platform approach
-        println!("[RUST] [TORRENT] 🔍 Checking if Peerflix is installed...");
-
-        let peerflix_commands = if cfg!(target_os = "windows") {
-            vec![
-                "peerflix",
-                "peerflix.cmd",
-                "C:\\Users\\yoann\\AppData\\Roaming\\npm\\peerflix.cmd",
-            ]
-        } else {
-            vec![
-                "peerflix",                           // System PATH
-                "/usr/local/bin/peerflix",           // Common Linux location
-                "/usr/bin/peerflix",                 // Alternative Linux location
-                "/home/deck/.local/bin/peerflix",    // Steam Deck user install
-            ]
+        // Dropping the startup on cancel drops its session, which tears it down
+        let mut stopped_before = self.stopped_before.subscribe();
+        let result = tokio::select! {
+            result = async {
+                let request = FileRequest { file_idx, episode };
+                match reused {
+                    Some(stream_session) => self.switch_file(stream_session, request, &mut report, on_stats).await,
+                    None => self.start_torrent(id, &infohash, request, sources, &mut report, on_stats).await,
+                }
+            } => result,
+            _ = stopped_before.wait_for(|stopped_before| *stopped_before > id) => Err(DeckflixError::stream_startup(
+                StartupFailure::Cancelled,
+                "The stream was stopped before it started",
+            )),
         };
+        let result = match result {
+            Ok((session, started)) => {
+                *self.active.lock().await = Some(session);
+                Ok(started)
+            }
+            Err(e) => Err(e),
+        };
+
+        match &result {
+            Ok(started) => {
+                println!("[RUST] [TORRENT] 📺 Session {} ready for video player launch: {}", started.id, started.url);
+                report(StartupState::Ready { url: started.url.clone() });
+            }
+            Err(e) => {
+                println!("[RUST] [TORRENT] ❌ Startup failed: {}", e);
+                let reason = match e {
+                    DeckflixError::StreamStartup { reason, .. } => *reason,
+                    _ => StartupFailure::Engine,
+                };
+                report(StartupState::Failed {
+                    reason,
+                    message: e.message().to_string(),
+                });
+            }
+        }
+        result
+    }
 
-        let mut peerflix_found = false;
-        let mut working_command = "peerflix";
-
-        for cmd in &peerflix_commands {
-            println!("[RUST] [TORRENT] 🔍 Trying command: {}", cmd);
-            let check = Command::new(cmd)
-                .arg("--help")
-                .output();
-
-            if check.is_ok() {
-                println!("[RUST] [TORRENT] ✅ Peerflix found with command: {}", cmd);
-                peerflix_found = true;
-                working_command = cmd;
-                break;
-            } else {
-                println!("[RUST] [TORRENT] ❌ Command {} failed: {:?}", cmd, check.err());
+    // Plays another file of the streamed torrent without resolving it again. When
+    // that fails the session is kept as the active one, so its metadata and peers
+    // are still there for the next attempt.
+    async fn switch_file(
+        &self,
+        mut stream_session: StreamSession,
+        request: FileRequest,
+        report: &mut impl FnMut(StartupState),
+        on_stats: impl Fn(StreamStats) + Send + 'static,
+    ) -> Result<(StreamSession, StartedStream), DeckflixError> {
+        println!("[RUST] [TORRENT] ♻️  Switching files in session {} - metadata and peers are kept", stream_session.id());
+        let prepared = match self.session().await {
+            Ok(session) => self.prepare_torrent(session, &mut stream_session, request, report, on_stats).await,
+            Err(e) => Err(e),
+        };
+        match prepared {
+            Ok(started) => Ok((stream_session, started)),
+            Err(e) => {
+                println!("[RUST] [TORRENT] ♻️  Keeping session {} after the failed switch", stream_session.id());
+                *self.active.lock().await = Some(stream_session);
+                Err(e)
             }
         }
+    }
 
-        if !peerflix_found {
-            println!("[RUST] [TORRENT] ❌ Peerflix not found with any command!");
-            return Err("Peerflix not installed. Install with: npm install -g peerflix".to_string());
+    async fn start_torrent(
+        &self,
+        id: u64,
+        infohash: &str,
+        request: FileRequest,
+        sources: &[String],
+        report: &mut impl FnMut(StartupState),
+        on_stats: impl Fn(StreamStats) + Send + 'static,
+    ) -> Result<(StreamSession, StartedStream), DeckflixError> {
+        report(StartupState::ResolvingMetadata);
+        let session = self.session().await?;
+
+        // Held from here so the torrent's cached data can't be evicted while it starts
+        let cache_lease = self.cache.lease(infohash);
+
+        let magnet_link = magnet_link(infohash, sources);
+        println!("[RUST] [TORRENT] 🔍 Resolving torrent metadata: {}", magnet_link);
+
+        let mut added = self.add_torrent(session, infohash, &magnet_link).await?;
+
+        // The only torrents in the engine belong to stream sessions, so this one
+        // is from a session dropped without closing (a cancelled startup) whose
+        // removal is still pending. Remove it now and add it again: the engine
+        // never reuses ids, so the pending removal can't touch the new one.
+        if let AddTorrentResponse::AlreadyManaged(stale_id, _) = added {
+            println!("[RUST] [TORRENT] ♻️  Torrent {} is still being removed - adding it again", stale_id);
+            if let Err(e) = session.delete(TorrentIdOrHash::Id(stale_id), false).await {
+                println!("[RUST] [TORRENT] ⚠️  Failed to remove torrent {}: {:#}", stale_id, e);
+            }
+            added = self.add_torrent(session, infohash, &magnet_link).await?;
         }
+        let handle = added
+            .into_handle()
+            .ok_or_else(|| DeckflixError::stream_startup(StartupFailure::Engine, "Torrent session returned no handle"))?;
 
-        println!("[RUST] [TORRENT] ✅ Peerflix found");
+        // The torrent belongs to the stream session from here on
+        let stream_session = StreamSession::open(id, &self.records_dir, session.clone(), handle, cache_lease);
+        self.prepare_or_close(session, stream_session, request, report, on_stats).await
+    }
 
-        // Extract file index from magnet link if present (&so= parameter)
-        let file_index = if let Some(so_pos) = magnet_link.find("&so=") {
-            let start = so_pos + 4; // Skip "&so="
-            let end = magnet_link[start..].find('&').map(|p| start + p).unwrap_or(magnet_link.len());
-            magnet_link[start..end].parse::<usize>().ok()
-        } else {
-            None
+    // Adds the torrent once a peer sent its metadata
+    async fn add_torrent(&self, session: &Arc<Session>, infohash: &str, magnet_link: &str) -> Result<AddTorrentResponse, DeckflixError> {
+        let options = AddTorrentOptions {
+            overwrite: true,
+            output_folder: Some(self.cache.torrent_dir(infohash).to_string_lossy().into_owned()),
+            // Nothing is selected, created or downloaded until prepare_torrent picks
+            // the file: the addon's fileIdx may be out of range, which the engine
+            // would reject, and selecting every file would allocate the whole torrent
+            only_files: Some(Vec::new()),
+            ..Default::default()
         };
+        tokio::time::timeout(METADATA_TIMEOUT, session.add_torrent(AddTorrent::from_url(magnet_link), Some(options)))
+            .await
+            .map_err(|_| {
+                DeckflixError::stream_startup(
+                    StartupFailure::MetadataTimeout,
+                    format!("No peers sent the torrent metadata within {} seconds", METADATA_TIMEOUT.as_secs()),
+                )
+            })?
+            .map_err(|e| DeckflixError::stream_startup(StartupFailure::Engine, format!("Failed to add torrent: {:#}", e)))
+    }
 
-        // Start peerflix process
-        println!("[RUST] [TORRENT] 🚀 Starting Peerflix process...");
-        if let Some(idx) = file_index {
-            println!("[RUST] [TORRENT] 📂 File index detected: {} (will use Peerflix --select)", idx);
-            println!("[RUST] [TORRENT] Command: {} \"{}\" --port {} --select {} --not-on-top", working_command, magnet_link, self.stream_port, idx);
-        } else {
-            println!("[RUST] [TORRENT] Command: {} \"{}\" --port {} --not-on-top", working_command, magnet_link, self.stream_port);
+    // Closes a session just opened for a new torrent again if its file can't be started
+    async fn prepare_or_close(
+        &self,
+        session: &Arc<Session>,
+        mut stream_session: StreamSession,
+        request: FileRequest,
+        report: &mut impl FnMut(StartupState),
+        on_stats: impl Fn(StreamStats) + Send + 'static,
+    ) -> Result<(StreamSession, StartedStream), DeckflixError> {
+        match self.prepare_torrent(session, &mut stream_session, request, report, on_stats).await {
+            Ok(started) => Ok((stream_session, started)),
+            Err(e) => {
+                if let Err(close_error) = stream_session.close().await {
+                    println!("[RUST] [TORRENT] ⚠️  Failed to remove torrent after failed startup: {}", close_error);
+                }
+                Err(e)
+            }
         }
+    }
 
-        let mut command = Command::new(working_command);
-        command
-            .arg(&magnet_link)
-            .arg("--port")
-            .arg(self.stream_port.to_string())
-            .arg("--not-on-top"); // Don't prioritize latest pieces for better streaming
-
-        // Add file selection if we have a file index
-        if let Some(idx) = file_index {
-            command.arg("--select").arg(idx.to_string());
+    async fn prepare_torrent(
+        &self,
+        session: &Arc<Session>,
+        stream_session: &mut StreamSession,
+        request: FileRequest,
+        report: &mut impl FnMut(StartupState),
+        on_stats: impl Fn(StreamStats) + Send + 'static,
+    ) -> Result<StartedStream, DeckflixError> {
+        let handle = stream_session.handle().clone();
+        handle
+            .wait_until_initialized()
+            .await
+            .map_err(|e| DeckflixError::stream_startup(StartupFailure::Engine, format!("Failed to initialize torrent: {:#}", e)))?;
+
+        let files = torrent_files(&handle)?;
+        println!("[RUST] [TORRENT] ✅ Metadata resolved: {} ({} file(s))", handle.name().unwrap_or_default(), files.len());
+
+        let file = select_file(&files, request.file_idx, request.episode)
+            .ok_or_else(|| DeckflixError::stream_startup(StartupFailure::NoFiles, "Torrent contains no files"))?;
+        let (file_index, file_size) = (file.index, file.size);
+
+        // Only the played file is downloaded: the torrent was added with no file
+        // selected, and a reused session still has the previous one
+        if handle.only_files() != Some(vec![file_index]) {
+            let only_files = HashSet::from([file_index]);
+            session.update_only_files(&handle, &only_files).await.map_err(|e| {
+                DeckflixError::stream_startup(StartupFailure::Engine, format!("Failed to select file {}: {:#}", file_index, e))
+            })?;
         }
 
-        command.arg("--quiet"); // Reduce output noise
+        println!("[RUST] [TORRENT] 📦 Selected file #{}: {} ({} bytes)", file_index, file.path, file_size);
 
-        let child = command
-            .spawn()
-            .map_err(|e| format!("Failed to start peerflix: {}", e))?;
+        // Make room for the rest of the file, whatever part of it is already cached
+        let cached_bytes = handle.stats().file_progress.get(file_index).copied().unwrap_or(0);
+        self.cache.make_room(file_size.saturating_sub(cached_bytes)).await;
 
-        let pid = child.id();
-        println!("[RUST] [TORRENT] ✅ Peerflix started successfully (PID: {})", pid);
+        wait_for_peers(&handle, file_index, file_size, report).await?;
+        buffer_file(&handle, file_index, file_size, report).await?;
 
-        // Store process handle
-        *self.peerflix_process.lock().await = Some(child);
+        let file_name = Path::new(&file.path)
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_else(|| format!("file-{}", file_index));
+        stream_session
+            .serve(file_index, &file_name, file.path.clone(), on_stats)
+            .await
+    }
 
-        // Peerflix downloads to different locations based on OS
-        let torrent_dir = if cfg!(target_os = "windows") {
-            format!("C:\\tmp\\torrent-stream\\{}", infohash)
-        } else {
-            // Linux/Steam Deck - peerflix uses /tmp by default
-            format!("/tmp/torrent-stream/{}", infohash)
+    // The files of a torrent and the one select_file would play. The streamed
+    // torrent's metadata is reused; any other torrent's metadata is fetched from
+    // its peers without downloading or keeping anything.
+    pub async fn list_files(
+        &self,
+        info_hash: &str,
+        file_idx: Option<u32>,
+        episode: Option<EpisodeRef>,
+        sources: &[String],
+    ) -> Result<TorrentFileList, DeckflixError> {
+        let infohash = normalize_info_hash(info_hash)
+            .ok_or_else(|| DeckflixError::invalid_request(format!("Invalid infohash: {}", info_hash)))?;
+
+        let streamed = {
+            let active = self.active.lock().await;
+            active
+                .as_ref()
+                .map(|session| session.handle().clone())
+                .filter(|handle| handle.info_hash().as_string() == infohash)
         };
 
-        println!("[RUST] [TORRENT] 📂 Torrent directory: {}", torrent_dir);
-        println!("[RUST] [TORRENT] ⏳ Waiting for Peerflix to create torrent directory...");
+        let (name, files) = match streamed {
+            Some(handle) => (handle.name(), torrent_files(&handle)?),
+            None => {
+                let session = self.session().await?;
+                let magnet_link = magnet_link(&infohash, sources);
+                println!("[RUST] [TORRENT] 📋 Listing files of {}", magnet_link);
+
+                let options = AddTorrentOptions {
+                    list_only: true,
+                    ..Default::default()
+                };
+                let added = tokio::time::timeout(METADATA_TIMEOUT, session.add_torrent(AddTorrent::from_url(magnet_link), Some(options)))
+                    .await
+                    .map_err(|_| DeckflixError::torrent(format!("No peers sent the torrent metadata within {} seconds", METADATA_TIMEOUT.as_secs())))?
+                    .map_err(|e| DeckflixError::torrent(format!("Failed to resolve torrent: {:#}", e)))?;
+                match added {
+                    AddTorrentResponse::ListOnly(listed) => {
+                        let name = listed.info.name.as_ref().map(|name| String::from_utf8_lossy(name.as_ref()).into_owned());
+                        let files = listed
+                            .info
+                            .iter_file_details()
+                            .map_err(|e| DeckflixError::torrent(format!("Invalid torrent metadata: {:#}", e)))?
+                            .enumerate()
+                            .filter(|(_, details)| !details.attrs().padding)
+                            .map(|(index, details)| {
+                                let path = details.filename.to_pathbuf().unwrap_or_else(|_| PathBuf::from(format!("file-{}", index)));
+                                torrent_file(index, &path, details.len)
+                            })
+                            .collect();
+                        (name, files)
+                    }
+                    // Being downloaded already, e.g. a stream that is still starting
+                    AddTorrentResponse::AlreadyManaged(_, handle) | AddTorrentResponse::Added(_, handle) => {
+                        (handle.name(), torrent_files(&handle)?)
+                    }
+                }
+            }
+        };
+
+        let selected = select_file(&files, file_idx, episode).map(|file| file.index);
+        println!("[RUST] [TORRENT] ✅ {} file(s) in {}, selected: {:?}", files.len(), infohash, selected);
+        Ok(TorrentFileList {
+            info_hash: infohash,
+            name,
+            files,
+            selected,
+        })
+    }
 
-        // Wait for peerflix to initialize and create the directory
-        tokio::time::sleep(tokio::time::Duration::from_secs(5)).await;
+    // Stops the current stream and cancels one that is still starting
+    pub async fn stop_stream(&self) -> Result<(), DeckflixError> {
+        println!("[RUST] [TORRENT] 🛑 Stopping torrent stream...");
+        self.stopped_before.send_replace(self.next_id.load(Ordering::SeqCst));
 
-        // Return the torrent directory path - we'll find the video file later
-        *self.stream_url.lock().await = Some(torrent_dir.clone());
+        let active = self.active.lock().await.take();
+        let Some(active) = active else {
+            println!("[RUST] [TORRENT] 📋 No active torrent to stop");
+            return Ok(());
+        };
 
-        println!("[RUST] [TORRENT] 📺 Ready for video player launch");
-        Ok(torrent_dir)
+        active.close().await?;
+        println!("[RUST] [TORRENT] ✅ Stream cleanup completed");
+        Ok(())
     }
 
-    async fn test_stream_availability(&self, url: &str) -> Result<(), String> {
-        // Try to make a HEAD request to test if the stream is responding
-        let client = reqwest::Client::new();
-        let response = client
-            .head(url)
-            .timeout(std::time::Duration::from_secs(2))
-            .send()
-            .await
-            .map_err(|e| format!("Stream test failed: {}", e))?;
-
-        if response.status().is_success() {
-            Ok(())
-        } else {
-            Err(format!("Stream returned status: {}", response.status()))
+    // The player reading session `session_id` is stopped with it
+    pub async fn attach_player(&self, session_id: u64, pid: u32) {
+        let mut active = self.active.lock().await;
+        match active.as_mut().filter(|session| session.id() == session_id) {
+            Some(session) => session.attach_player(pid),
+            // The session already ended; so should its player
+            None => {
+                println!("[RUST] [TORRENT] ⚠️  Session {} ended before its player started", session_id);
+            }
         }
     }
 
-    pub async fn stop_stream(&self) -> Result<(), String> {
-        println!("[RUST] [TORRENT] 🛑 Stopping torrent stream...");
+    pub async fn stats(&self) -> Option<StreamStats> {
+        let active = self.active.lock().await;
+        active.as_ref()?.stats()
+    }
 
-        let mut process = self.peerflix_process.lock().await;
-        if let Some(mut child) = process.take() {
-            println!("[RUST] [TORRENT] 🔄 Terminating Peerflix process (PID: {})", child.id());
+    pub async fn is_streaming(&self) -> bool {
+        self.active.lock().await.is_some()
+    }
+}
 
-            match child.kill() {
-                Ok(_) => {
-                    println!("[RUST] [TORRENT] ✅ Peerflix process terminated successfully");
+fn live_peers(handle: &ManagedTorrent) -> usize {
+    handle
+        .stats()
+        .live
+        .map(|live| live.snapshot.peer_stats.live)
+        .unwrap_or(0)
+}
 
-                    // Wait for process to fully exit
-                    match child.wait() {
-                        Ok(status) => println!("[RUST] [TORRENT] 📋 Process exit status: {}", status),
-                        Err(e) => println!("[RUST] [TORRENT] ⚠️  Process wait error: {}", e),
-                    }
-                }
-                Err(e) => {
-                    println!("[RUST] [TORRENT] ❌ Failed to kill Peerflix process: {}", e);
-                    return Err(format!("Failed to kill peerflix: {}", e));
+// Peers are not needed for a file that is already downloaded; a finished
+// torrent also drops its seeds, as when switching to an episode that
+// completed before the first check
+async fn wait_for_peers(
+    handle: &ManagedTorrent,
+    file_index: usize,
+    file_size: u64,
+    report: &mut impl FnMut(StartupState),
+) -> Result<(), DeckflixError> {
+    let started = Instant::now();
+    loop {
+        let peers = live_peers(handle);
+        report(StartupState::ConnectingPeers { peers });
+        if peers > 0 {
+            println!("[RUST] [TORRENT] 🤝 Connected to {} peer(s)", peers);
+            return Ok(());
+        }
+        if handle.stats().file_progress.get(file_index).copied().unwrap_or(0) >= file_size {
+            println!("[RUST] [TORRENT] 💾 File #{} already downloaded", file_index);
+            return Ok(());
+        }
+        if started.elapsed() >= PEER_TIMEOUT {
+            return Err(DeckflixError::stream_startup(
+                StartupFailure::NoPeers,
+                format!("No peers connected within {} seconds", PEER_TIMEOUT.as_secs()),
+            ));
+        }
+        tokio::time::sleep(STARTUP_TICK).await;
+    }
+}
+
+// Reads the start and end of the file the way the player will, so buffering is
+// only done once those pieces are downloaded and verified
+async fn buffer_file(
+    handle: &Arc<ManagedTorrent>,
+    file_index: usize,
+    file_size: u64,
+    report: &mut impl FnMut(StartupState),
+) -> Result<(), DeckflixError> {
+    let head = HEAD_BUFFER_BYTES.min(file_size);
+    let tail = TAIL_BUFFER_BYTES.min(file_size - head);
+    let target_bytes = head + tail;
+    let buffered = AtomicU64::new(0);
+
+    println!("[RUST] [TORRENT] ⏳ Buffering {} bytes...", target_bytes);
+
+    let reads = async {
+        tokio::try_join!(
+            read_range(handle.clone(), file_index, 0, head, &buffered),
+            read_range(handle.clone(), file_index, file_size - tail, tail, &buffered),
+        )
+    };
+    tokio::pin!(reads);
+
+    let mut ticker = tokio::time::interval(STARTUP_TICK);
+    let mut last_buffered = 0;
+    let mut last_progress = Instant::now();
+    loop {
+        tokio::select! {
+            result = &mut reads => {
+                println!("[RUST] [TORRENT] ✅ Buffering complete");
+                return result.map(|_| ()).map_err(|e| {
+                    DeckflixError::stream_startup(StartupFailure::Engine, format!("Failed to read the torrent file: {}", e))
+                });
+            }
+            _ = ticker.tick() => {
+                let buffered_bytes = buffered.load(Ordering::Relaxed);
+                report(StartupState::Buffering {
+                    buffered_bytes,
+                    target_bytes,
+                    peers: live_peers(handle),
+                });
+
+                if buffered_bytes > last_buffered {
+                    last_buffered = buffered_bytes;
+                    last_progress = Instant::now();
+                } else if last_progress.elapsed() >= BUFFER_STALL_TIMEOUT {
+                    return Err(DeckflixError::stream_startup(
+                        StartupFailure::BufferStalled,
+                        format!(
+                            "No data arrived for {} seconds ({} of {} bytes buffered)",
+                            BUFFER_STALL_TIMEOUT.as_secs(),
+                            buffered_bytes,
+                            target_bytes
+                        ),
+                    ));
                 }
             }
-        } else {
-            println!("[RUST] [TORRENT] 📋 No active Peerflix process to stop");
         }
+    }
+}
 
-        *self.stream_url.lock().await = None;
-        println!("[RUST] [TORRENT] ✅ Stream cleanup completed");
-        Ok(())
+async fn read_range(handle: Arc<ManagedTorrent>, file_index: usize, start: u64, len: u64, buffered: &AtomicU64) -> std::io::Result<()> {
+    if len == 0 {
+        return Ok(());
+    }
+
+    let mut file_stream = handle
+        .stream(file_index)
+        .map_err(|e| std::io::Error::other(format!("{:#}", e)))?;
+    file_stream.seek(SeekFrom::Start(start)).await?;
+
+    let mut range = file_stream.take(len);
+    let mut buf = vec![0u8; 256 * 1024];
+    loop {
+        let read = range.read(&mut buf).await?;
+        if read == 0 {
+            return Ok(());
+        }
+        buffered.fetch_add(read as u64, Ordering::Relaxed);
     }
+}
 
-    pub async fn get_stream_url(&self) -> Option<String> {
-        self.stream_url.lock().await.clone()
+// Every file in a resolved torrent but padding files, in torrent order
+pub fn torrent_files(handle: &ManagedTorrent) -> Result<Vec<TorrentFile>, DeckflixError> {
+    handle
+        .with_metadata(|metadata| {
+            metadata
+                .file_infos
+                .iter()
+                .enumerate()
+                .filter(|(_, f)| !f.attrs.padding)
+                .map(|(index, f)| torrent_file(index, &f.relative_filename, f.len))
+                .collect()
+        })
+        .map_err(|e| DeckflixError::torrent(format!("Torrent metadata unavailable: {:#}", e)))
+}
+
+// Which pieces of a torrent are downloaded and hash-checked, one flag per piece,
+// from the engine's have-bitfield. librqbit only hands it out as the text its
+// HTTP API serves: "BitSlice<u8, Msb0> { addr: .., head: .., bits: 3 } [1, 0, 1]".
+pub fn have_pieces(engine: &Arc<Session>, handle: &ManagedTorrent) -> Option<Vec<bool>> {
+    let dump = Api::new(engine.clone(), None)
+        .api_dump_haves(TorrentIdOrHash::Id(handle.id()))
+        .ok()?;
+    let (_, bits) = dump.split_once("} [")?;
+    let bits = bits.strip_suffix(']')?;
+    if bits.is_empty() {
+        return Some(Vec::new());
     }
+    bits.split(", ")
+        .map(|bit| match bit {
+            "0" => Some(false),
+            "1" => Some(true),
+            _ => None,
+        })
+        .collect()
+}
 
-    pub async fn is_streaming(&self) -> bool {
-        let process = self.peerflix_process.lock().await;
-        process.is_some()
+// The pieces a file of the torrent spans
+pub fn file_pieces(handle: &ManagedTorrent, file_index: usize) -> Result<Range<usize>, DeckflixError> {
+    handle
+        .with_metadata(|metadata| {
+            metadata
+                .file_infos
+                .get(file_index)
+                .map(|f| f.piece_range_usize())
+        })
+        .map_err(|e| DeckflixError::torrent(format!("Torrent metadata unavailable: {:#}", e)))?
+        .ok_or_else(|| DeckflixError::torrent(format!("Torrent has no file #{}", file_index)))
+}
+
+fn torrent_file(index: usize, path: &Path, size: u64) -> TorrentFile {
+    let is_video = is_video_file(path);
+    let path = path.to_string_lossy().into_owned();
+    let (season, episode) = release_parser::file_episode(&path);
+    TorrentFile {
+        index,
+        path,
+        is_video,
+        size,
+        season,
+        episode,
     }
 }
 
-impl Drop for TorrentStreamer {
-    fn drop(&mut self) {
-        // Ensure cleanup happens when the struct is dropped
-        println!("[RUST] [TORRENT] 🧹 TorrentStreamer dropping - cleaning up resources");
+fn is_video_file(path: &Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .map(|ext| VIDEO_EXTENSIONS.contains(&ext.to_lowercase().as_str()))
+        .unwrap_or(false)
+}
+
+// The file at `file_idx` when it exists and its name doesn't say it's another
+// episode than `episode`; otherwise the largest video file of that episode, then
+// the file at `file_idx` anyway, then the largest video file (or the largest
+// file when there are no videos)
+pub fn select_file(files: &[TorrentFile], file_idx: Option<u32>, episode: Option<EpisodeRef>) -> Option<&TorrentFile> {
+    let indexed = file_idx.and_then(|idx| files.iter().find(|file| file.index == idx as usize));
+    let largest = |matches: &dyn Fn(&TorrentFile) -> bool| files.iter().filter(|file| matches(file)).max_by_key(|file| file.size);
+
+    if let Some(episode) = episode {
+        let is_episode = |file: &TorrentFile| {
+            file.episode == Some(episode.episode) && file.season.is_none_or(|season| season == episode.season)
+        };
+        if let Some(file) = indexed.filter(|file| file.episode.is_none() || is_episode(file)) {
+            return Some(file);
+        }
+        if let Some(file) = largest(&|file| file.is_video && is_episode(file)) {
+            return Some(file);
+        }
     }
-}
\ No newline at end of file
+
+    indexed
+        .or_else(|| largest(&|file| file.is_video))
+        .or_else(|| largest(&|_| true))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Indices out of order, as in a real pack
+    fn pack() -> Vec<TorrentFile> {
+        [
+            "Show.S01/Show.S01E03.mkv",
+            "Show.S01/Show.S01E01.mkv",
+            "Show.S01/Sample/sample.mkv",
+            "Show.S01/Show.nfo",
+            "Show.S01/Show.S01E02.mkv",
+        ]
+        .iter()
+        .enumerate()
+        .map(|(index, path)| torrent_file(index, Path::new(path), if path.contains("sample") { 10 } else { 100 + index as u64 }))
+        .collect()
+    }
+
+    fn episode(season: u32, episode: u32) -> Option<EpisodeRef> {
+        Some(EpisodeRef { season, episode })
+    }
+
+    #[test]
+    fn picks_the_episode_over_a_wrong_index() {
+        let files = pack();
+        assert_eq!(select_file(&files, Some(1), episode(1, 2)).map(|f| f.index), Some(4));
+        assert_eq!(select_file(&files, None, episode(1, 1)).map(|f| f.index), Some(1));
+        assert_eq!(select_file(&files, Some(99), episode(1, 3)).map(|f| f.index), Some(0));
+    }
+
+    #[test]
+    fn keeps_an_index_that_fits_the_episode() {
+        let files = pack();
+        assert_eq!(select_file(&files, Some(4), episode(1, 2)).map(|f| f.index), Some(4));
+        // Files without an episode in their name are trusted
+        assert_eq!(select_file(&files, Some(3), episode(1, 2)).map(|f| f.index), Some(3));
+    }
+
+    #[test]
+    fn falls_back_to_the_largest_video() {
+        let files = pack();
+        assert_eq!(select_file(&files, None, None).map(|f| f.index), Some(4));
+        assert_eq!(select_file(&files, None, episode(2, 1)).map(|f| f.index), Some(4));
+        assert_eq!(select_file(&files, Some(2), None).map(|f| f.index), Some(2));
+        let extras = vec![torrent_file(0, Path::new("a.nfo"), 5), torrent_file(1, Path::new("b.srt"), 7)];
+        assert_eq!(select_file(&extras, None, None).map(|f| f.index), Some(1));
+        assert_eq!(select_file(&[], None, None).map(|f| f.index), None);
+    }
+}
@@ -8,6 +8,7 @@ let appState = {
   searchResults: [],
   continueWatching: [],
   currentContent: null,
+  currentEpisode: null,  // { season, episode } while picking a stream for an episode
   currentStreams: [],
   isLoading: false,
   isSearching: false,
@@ -16,7 +17,9 @@ let appState = {
   currentSection: 'movies',
   searchQuery: '',
   searchTimeout: null,
-  searchFilter: 'all'  // 'all', 'movies', 'series', 'anime'
+  searchFilter: 'all',  // 'all', 'movies', 'series', 'anime'
+  downloads: [],  // offline downloads, kept current by "download-progress"
+  activeInfoHash: null  // torrent being streamed, whose other files start instantly
 };
 
 // DOM elements
@@ -151,6 +154,7 @@ async function initApp() {
       seasonSelect: document.getElementById('season-select'),
       episodesList: document.getElementById('episodes-list'),
       episodeLoading: document.getElementById('episode-loading'),
+      episodeSelector: document.getElementById('episode-selector'),
       episodeError: document.getElementById('episode-error'),
 
       // Stream modal elements
@@ -202,10 +206,11 @@ async function initApp() {
     // Check addon status
     await checkAddonStatus();
 
+    DEBUG.log('APP_INIT', 'Loading downloads...');
+    // Load offline downloads and follow their progress
+    await loadDownloads();
+
     DEBUG.log('APP_INIT', 'Loading continue watching data...');
-    // TEMPORARY: Clear localStorage to remove any cached bad data
-    console.log('🧹 Clearing localStorage to fix ID caching issue...');
-    localStorage.clear();
     // Load continue watching from localStorage
     loadContinueWatching();
 
@@ -271,8 +276,8 @@ function setupEventListeners() {
   elements.closeVideo.addEventListener('click', closeVideoPlayer);
   elements.retryVideo.addEventListener('click', () => {
     // Try external player as fallback
-    if (window.currentStreamUrl) {
-      tryExternalPlayer(window.currentStreamUrl);
+    if (window.currentStream) {
+      tryExternalPlayer(window.currentStream);
     }
   });
 
@@ -1751,7 +1756,8 @@ function loadContinueWatching() {
   try {
     const stored = localStorage.getItem(CONTINUE_WATCHING_KEY);
     if (stored) {
-      appState.continueWatching = JSON.parse(stored);
+      // Drop entries saved without a usable ID by older versions
+      appState.continueWatching = JSON.parse(stored).filter(item => item && typeof item.id === 'string' && item.id);
       displayContinueWatching();
     }
   } catch (error) {
@@ -1839,6 +1845,7 @@ async function selectContent(content, contentType) {
   DEBUG.log('SELECT_CONTENT', `Selected: ${content.name}, Type: ${contentType}, content.type: ${content.type}`);
 
   appState.currentContent = content;
+  appState.currentEpisode = null;
 
   // Check if it's a series or anime - show episode picker
   // Check both the passed contentType and the content.type field
@@ -1862,57 +1869,83 @@ async function selectContent(content, contentType) {
   }
 }
 
-function showEpisodePicker(content, contentType) {
+async function showEpisodePicker(content, contentType) {
   DEBUG.log('EPISODE_PICKER', `Showing episode picker for: ${content.name}`);
 
   // Show episode modal
   elements.episodeModalTitle.textContent = content.name;
   elements.episodeModal.classList.remove('hidden');
 
-  // For now, generate simple season/episode options (1-10 seasons, 1-20 episodes each)
-  // TODO: In future, fetch actual episode data from API
   const seasonSelect = elements.seasonSelect;
   seasonSelect.innerHTML = '';
+  elements.episodesList.innerHTML = '';
+
+  // Show loading while the episode list is fetched
+  elements.episodeLoading.classList.remove('hidden');
+  elements.episodeSelector.classList.add('hidden');
+  elements.episodeError.classList.add('hidden');
+
+  let seasons;
+  try {
+    // Anime from Cinemeta uses the regular series meta
+    const details = await safeInvoke('fetch_meta', { contentType: 'series', id: content.id });
+    seasons = details.seasons;
+    DEBUG.log('EPISODE_PICKER', `Received ${seasons.length} seasons for ${content.name}`);
+
+    if (seasons.length === 0) {
+      throw new Error('No episodes available for this series');
+    }
+  } catch (error) {
+    DEBUG.error('EPISODE_PICKER', 'Failed to load episodes', error);
+    elements.episodeLoading.classList.add('hidden');
+    elements.episodeError.classList.remove('hidden');
+    return;
+  }
+
+  elements.episodeLoading.classList.add('hidden');
+  elements.episodeSelector.classList.remove('hidden');
 
-  // Generate seasons (1-10)
-  for (let season = 1; season <= 10; season++) {
+  seasons.forEach((season, index) => {
     const option = document.createElement('option');
-    option.value = season;
-    option.textContent = `Season ${season}`;
+    option.value = index;
+    option.textContent = season.number === 0 ? 'Specials' : `Season ${season.number}`;
     seasonSelect.appendChild(option);
-  }
+  });
 
   // Function to display episodes for selected season
   function displayEpisodesForSeason(season) {
     const episodesList = elements.episodesList;
     episodesList.innerHTML = '';
 
-    // Generate episodes (1-20)
-    for (let episode = 1; episode <= 20; episode++) {
+    season.episodes.forEach(ep => {
       const episodeItem = document.createElement('div');
       episodeItem.className = 'episode-item focusable';
       episodeItem.tabIndex = 0;
-      episodeItem.dataset.season = season;
-      episodeItem.dataset.episode = episode;
+      episodeItem.dataset.season = ep.season;
+      episodeItem.dataset.episode = ep.episode;
 
       const episodeTitle = document.createElement('div');
       episodeTitle.className = 'episode-title';
-      episodeTitle.textContent = `Episode ${episode}`;
+      episodeTitle.textContent = ep.title;
+      if (ep.overview) {
+        episodeItem.title = ep.overview;
+      }
 
       const episodeNum = document.createElement('div');
       episodeNum.className = 'episode-number';
-      episodeNum.textContent = `S${season}E${episode}`;
+      const airDate = ep.released ? ` • ${new Date(ep.released).toLocaleDateString()}` : '';
+      episodeNum.textContent = `S${ep.season}E${ep.episode}${airDate}`;
 
       episodeItem.appendChild(episodeTitle);
       episodeItem.appendChild(episodeNum);
 
       // Click handler for episode
       episodeItem.addEventListener('click', () => {
-        selectEpisode(content, contentType, season, episode);
+        selectEpisode(content, contentType, ep.season, ep.episode);
       });
 
       episodesList.appendChild(episodeItem);
-    }
+    });
 
     // Focus first episode
     const firstEpisode = episodesList.querySelector('.episode-item');
@@ -1921,14 +1954,14 @@ function showEpisodePicker(content, contentType) {
     }
   }
 
-  // Initial display of season 1 episodes
-  displayEpisodesForSeason(1);
+  // Initial display of the first season
+  displayEpisodesForSeason(seasons[0]);
 
-  // Handle season change
-  seasonSelect.addEventListener('change', () => {
-    const selectedSeason = parseInt(seasonSelect.value);
+  // Handle season change (replaces the handler from a previous series)
+  seasonSelect.onchange = () => {
+    const selectedSeason = seasons[parseInt(seasonSelect.value)];
     displayEpisodesForSeason(selectedSeason);
-  });
+  };
 }
 
 async function selectEpisode(content, contentType, season, episode) {
@@ -1937,11 +1970,12 @@ async function selectEpisode(content, contentType, season, episode) {
   // Close episode picker
   elements.episodeModal.classList.add('hidden');
 
-  // Construct episode IMDB ID (format: tt1234567:season:episode)
+  // Episode video ID as used by Stremio (format: tt1234567:season:episode)
   const episodeId = `${content.id}:${season}:${episode}`;
 
   // Add to continue watching with episode info
   addToContinueWatching(content, contentType, 0, { season, episode });
+  appState.currentEpisode = { season, episode };
 
   // Show stream modal
   elements.modalMovieTitle.textContent = `${content.name} - S${season}E${episode}`;
@@ -1954,7 +1988,7 @@ async function selectEpisode(content, contentType, season, episode) {
 
   try {
     DEBUG.log('STREAMS', `Fetching streams for episode: ${episodeId}`);
-    const streams = await safeInvoke('fetch_streams', { imdbId: episodeId });
+    const streams = await fetchStreamsProgressively({ imdbId: content.id, season, episode }, episodeId);
 
     DEBUG.log('STREAMS', `Received ${streams.length} streams`);
     appState.currentStreams = streams;
@@ -1986,7 +2020,7 @@ async function showStreamsForMovie(content, contentType) {
 
   try {
     DEBUG.log('STREAMS', `Fetching streams for movie: ${content.id}`);
-    const streams = await safeInvoke('fetch_streams', { imdbId: content.id });
+    const streams = await fetchStreamsProgressively({ imdbId: content.id }, content.id);
 
     DEBUG.log('STREAMS', `Received ${streams.length} streams`);
     appState.currentStreams = streams;
@@ -2003,6 +2037,45 @@ async function showStreamsForMovie(content, contentType) {
   }
 }
 
+// Fetch streams and show each addon's results as soon as it answers.
+// Resolves with the final ranking once every addon has answered or timed out.
+async function fetchStreamsProgressively(args, videoId) {
+  const { listen } = window.__TAURI__.event;
+  let ranking = [];
+  let resolveComplete;
+  const completed = new Promise(resolve => { resolveComplete = resolve; });
+
+  const unlistenProgress = await listen('streams-progress', (event) => {
+    const progress = event.payload;
+    if (progress.video_id !== videoId) return;
+
+    const source = progress.source;
+    DEBUG.log('STREAMS', `${source.addon_name}: ${source.success ? `${source.stream_count} streams` : `failed (${source.error})`}`);
+
+    ranking = progress.ranking;
+    appState.currentStreams = ranking;
+    if (ranking.length > 0) {
+      displayStreams(ranking);
+    }
+  });
+
+  const unlistenComplete = await listen('streams-complete', (event) => {
+    if (event.payload.video_id === videoId) {
+      resolveComplete(event.payload);
+    }
+  });
+
+  try {
+    await safeInvoke('fetch_streams_progressive', args);
+    const complete = await completed;
+    DEBUG.log('STREAMS', `All sources done: ${complete.total_streams} streams from ${complete.sources.filter(s => s.success).length}/${complete.sources.length} sources`);
+    return ranking;
+  } finally {
+    unlistenProgress();
+    unlistenComplete();
+  }
+}
+
 function displayStreams(streams) {
   // Hide loading
   elements.streamsLoading.classList.add('hidden');
@@ -2010,8 +2083,13 @@ function displayStreams(streams) {
   // Clear existing streams
   elements.streamsList.innerHTML = '';
 
+  // Other files of the torrent being streamed start without reconnecting,
+  // e.g. the next episode of a season pack, so they are listed first
+  const active = streams.filter(stream => isActiveTorrent(stream));
+  const others = streams.filter(stream => !isActiveTorrent(stream));
+
   // Create stream items
-  streams.forEach((stream, index) => {
+  [...active, ...others].forEach((stream, index) => {
     const streamItem = createStreamItem(stream, index);
     elements.streamsList.appendChild(streamItem);
   });
@@ -2026,6 +2104,10 @@ function displayStreams(streams) {
   }
 }
 
+function isActiveTorrent(stream) {
+  return !!stream.info_hash && stream.info_hash.toLowerCase() === appState.activeInfoHash;
+}
+
 function createStreamItem(stream, index) {
   const item = document.createElement('div');
   item.className = 'stream-item focusable';
@@ -2038,11 +2120,53 @@ function createStreamItem(stream, index) {
 
   const quality = document.createElement('div');
   quality.className = 'stream-quality';
-  quality.textContent = extractQuality(stream.title) || 'Quality: Unknown';
+  quality.textContent = stream.quality ? `Quality: ${stream.quality}` : (extractQuality(stream.title) || 'Quality: Unknown');
 
   item.appendChild(title);
   item.appendChild(quality);
 
+  const badges = createReleaseBadges(stream.release);
+  if (badges) {
+    item.appendChild(badges);
+  }
+
+  if (isActiveTorrent(stream)) {
+    const instant = document.createElement('div');
+    instant.className = 'stream-badges';
+    instant.innerHTML = '<span class="stream-badge instant">Instant - already streaming</span>';
+    item.appendChild(instant);
+  }
+
+  if (stream.addons && stream.addons.length > 0) {
+    const sources = document.createElement('div');
+    sources.className = 'stream-quality';
+    sources.textContent = `From: ${stream.addons.join(', ')}`;
+    item.appendChild(sources);
+  }
+
+  const download = document.createElement('button');
+  download.className = 'stream-download focusable';
+  const existing = findDownload(stream);
+  download.textContent = existing ? describeDownload(existing) : 'Download';
+  download.addEventListener('click', (e) => {
+    e.stopPropagation();
+    downloadStream(stream);
+  });
+  item.appendChild(download);
+
+  // Season packs and other multi-file torrents: pick the file to play
+  if (stream.info_hash) {
+    const files = document.createElement('button');
+    files.className = 'stream-download focusable';
+    files.style.marginLeft = '8px';
+    files.textContent = 'Choose file';
+    files.addEventListener('click', (e) => {
+      e.stopPropagation();
+      showFilePicker(stream);
+    });
+    item.appendChild(files);
+  }
+
   // Add click listener
   item.addEventListener('click', () => {
     console.log('Stream item clicked:', stream.title);
@@ -2052,6 +2176,35 @@ function createStreamItem(stream, index) {
   return item;
 }
 
+// Badges for what the release name says: resolution, source, HDR, codec, audio, languages
+function createReleaseBadges(release) {
+  if (!release) return null;
+
+  const labels = [
+    release.resolution,
+    release.source,
+    ...(release.hdr || []),
+    release.video_codec && (release.bit_depth ? `${release.video_codec} ${release.bit_depth}-bit` : release.video_codec),
+    [release.audio_codec, release.audio_channels].filter(Boolean).join(' '),
+    release.atmos ? 'Atmos' : null,
+    ...(release.languages || []),
+    release.release_group
+  ].filter(Boolean);
+
+  if (labels.length === 0) return null;
+
+  const container = document.createElement('div');
+  container.className = 'stream-badges';
+  const lowQualitySources = ['CAM', 'TS', 'TC', 'SCR'];
+  labels.forEach(label => {
+    const badge = document.createElement('span');
+    badge.className = lowQualitySources.includes(label) ? 'stream-badge warning' : 'stream-badge';
+    badge.textContent = label;
+    container.appendChild(badge);
+  });
+  return container;
+}
+
 function extractQuality(title) {
   if (!title) return null;
 
@@ -2088,18 +2241,33 @@ async function playStream(stream) {
     console.log('📦 [PLAY_STREAM] Full stream object:', JSON.stringify(stream, null, 2));
     console.log('═══════════════════════════════════════════════════════════');
 
-    // Store current stream URL for retry functionality
-    window.currentStreamUrl = stream.url;
+    // Store current stream for retry functionality
+    window.currentStream = stream;
+
+    // Downloaded streams are played from disk by the external player
+    const download = findDownload(stream);
+    if (download && download.status === 'completed') {
+      console.log('💾 Playing downloaded copy:', download.file_path);
+      tryExternalPlayer(stream);
+      closeStreamModal();
+      return;
+    }
+
+    // The built-in player can't send proxy headers or decode streams the addon marked notWebReady
+    const hints = stream.behavior_hints || {};
+    const needsExternalPlayer = hints.notWebReady ||
+      Object.keys(hints.proxyHeaders?.request || {}).length > 0;
 
-    // Check if it's a direct video URL that can be played in HTML5 video
-    if (stream.url && (stream.url.startsWith('http://') || stream.url.startsWith('https://')) &&
-        !stream.url.startsWith('magnet:') &&
+    if (needsExternalPlayer && !stream.info_hash) {
+      console.log('🎛️ Stream needs an external player (notWebReady or proxy headers)');
+      tryExternalPlayer(stream);
+    } else if (stream.url && (stream.url.startsWith('http://') || stream.url.startsWith('https://')) &&
         (stream.url.includes('.mp4') || stream.url.includes('.mkv') || stream.url.includes('.webm') || stream.url.includes('video'))) {
       console.log('🎬 Using built-in video player for direct video URL');
       playWithBuiltInPlayer(stream);
-    } else if (stream.url && stream.url.startsWith('magnet:')) {
-      console.log('🧲 Using external player for magnet link');
-      tryExternalPlayer(stream.url);
+    } else if (stream.info_hash) {
+      console.log('🧲 Using external player for torrent', stream.info_hash, 'file', stream.file_idx);
+      tryExternalPlayer(stream);
     } else {
       console.log('❓ Unknown stream type, trying external player');
       console.log('🔍 URL details:', {
@@ -2108,7 +2276,7 @@ async function playStream(stream) {
         includesVideo: stream.url?.includes('video'),
         includesMp4: stream.url?.includes('.mp4')
       });
-      tryExternalPlayer(stream.url);
+      tryExternalPlayer(stream);
     }
 
     // Close stream selection modal
@@ -2174,12 +2342,14 @@ function playWithBuiltInPlayer(stream) {
 }
 
 // External player fallback function
-async function tryExternalPlayer(streamUrl) {
+// `fileIndex` plays that file of a torrent instead of the one matching the episode
+async function tryExternalPlayer(stream, fileIndex = null) {
+  let unlistenStartup = null;
   try {
-    console.log('Attempting external video player for:', streamUrl);
+    console.log('Attempting external video player for:', stream.url || `torrent ${stream.info_hash}`);
 
     // Show appropriate loading state based on stream type
-    const isMagnet = streamUrl.startsWith('magnet:');
+    const isMagnet = !!stream.info_hash;
     const loadingMsg = document.createElement('div');
     loadingMsg.id = 'stream-status';
 
@@ -2188,7 +2358,7 @@ async function tryExternalPlayer(streamUrl) {
         <div style="text-align: center;">
           <div class="loading-spinner" style="margin: 0 auto 15px auto;"></div>
           <div style="font-size: 18px; margin-bottom: 10px;">Starting torrent stream...</div>
-          <div style="font-size: 14px; color: var(--text-secondary);">Setting up Peerflix and downloading...</div>
+          <div id="torrent-startup-detail" style="font-size: 14px; color: var(--text-secondary);">Finding peers and downloading metadata...</div>
           <div style="font-size: 12px; color: var(--text-secondary); margin-top: 10px;">This may take a few seconds</div>
         </div>
       `;
@@ -2217,44 +2387,359 @@ async function tryExternalPlayer(streamUrl) {
     `;
     document.body.appendChild(loadingMsg);
 
-    const result = await safeInvoke('play_video_external', { streamUrl: streamUrl });
+    // The backend reports each torrent startup step until the stream is ready or fails
+    if (isMagnet) {
+      const { listen } = window.__TAURI__.event;
+      const infoHash = stream.info_hash.toLowerCase();
+      unlistenStartup = await listen('torrent-startup', (event) => {
+        if (event.payload.info_hash !== infoHash) return;
+        const detail = document.getElementById('torrent-startup-detail');
+        if (detail) detail.textContent = describeTorrentStartup(event.payload);
+      });
+    }
+
+    const result = await safeInvoke('play_video_external', {
+      stream: stream,
+      episode: appState.currentEpisode,
+      fileIndex: fileIndex
+    });
     console.log('Play video result:', result);
 
     // Remove loading message
     hideStatus();
 
-    // For magnet links, Peerflix returns the local HTTP stream URL
-    // Play it in the built-in player
+    // If the torrent engine returns a local HTTP stream URL, play it in the built-in player
     if (isMagnet && result && typeof result === 'string' && result.startsWith('http://127.0.0.1:')) {
-      console.log('🎬 Opening Peerflix stream in built-in player:', result);
+      console.log('🎬 Opening torrent stream in built-in player:', result);
 
       // Create a fake stream object for the built-in player
-      const peerflixStream = {
-        title: 'Torrent Stream (Peerflix)',
+      const torrentStream = {
+        title: 'Torrent Stream',
         url: result
       };
 
-      playWithBuiltInPlayer(peerflixStream);
+      playWithBuiltInPlayer(torrentStream);
     } else if (isMagnet) {
       // Fallback: If we didn't get a URL, show success message
       showStatus('Torrent stream started!', 3000);
+      showStreamStats(stream.info_hash.toLowerCase());
     }
 
   } catch (error) {
+    // Another stream replaced this one while it was starting; its overlay is the one showing now
+    if (error.kind === 'stream_startup' && error.reason === 'cancelled') {
+      console.log('Torrent stream startup was cancelled');
+      return;
+    }
+
+    // A failed torrent startup leaves no stream running
+    if (stream.info_hash) {
+      appState.activeInfoHash = null;
+    }
+
     // Remove loading message if it exists
     hideStatus();
 
     console.error('Failed to launch external player:', error);
 
-    // Show user-friendly error message
+    // Show user-friendly error message based on the backend error kind
     const errorMsg = error.message || error;
-    if (errorMsg === 'undefined' || errorMsg.includes('undefined')) {
-      showError('External video player not available. Please install MPV or VLC media player.');
-    } else if (errorMsg.includes('No video player found')) {
+    if (error.kind === 'stream_startup') {
+      showError(describeStartupFailure(error.reason, errorMsg));
+    } else if (error.kind === 'player_launch') {
       showError('No video player found. Please install:\n• Windows: VLC or MPV\n• Steam Deck: Install via Discover app');
+    } else if (error.kind === 'network') {
+      showError('Network problem while starting the stream. Check your connection and try again.');
+    } else if (errorMsg === 'undefined' || String(errorMsg).includes('undefined')) {
+      showError('External video player not available. Please install MPV or VLC media player.');
     } else {
       showError('Failed to launch external video player: ' + errorMsg);
     }
+  } finally {
+    if (unlistenStartup) unlistenStartup();
+  }
+}
+
+function describeTorrentStartup(startup) {
+  switch (startup.state) {
+    case 'resolving_metadata':
+      return 'Finding peers and downloading metadata...';
+    case 'connecting_peers':
+      return `Connecting to peers (${startup.peers} connected)...`;
+    case 'buffering': {
+      const percent = startup.target_bytes > 0
+        ? Math.floor(startup.buffered_bytes / startup.target_bytes * 100)
+        : 100;
+      return `Buffering ${percent}% (${startup.peers} peers)...`;
+    }
+    case 'ready':
+      return 'Starting player...';
+    case 'failed':
+      return startup.message;
+    default:
+      return '';
+  }
+}
+
+// Live download statistics of the torrent being streamed, updated from "stream-stats"
+// events until the stream is stopped or another one starts
+let unlistenStreamStats = null;
+
+async function showStreamStats(infoHash) {
+  hideStreamStats();
+  appState.activeInfoHash = infoHash;
+
+  const panel = document.createElement('div');
+  panel.id = 'stream-stats-panel';
+  panel.innerHTML = `
+    <div style="display: flex; justify-content: space-between; align-items: center; margin-bottom: 8px;">
+      <div id="stream-stats-title" style="font-size: 14px; overflow: hidden; text-overflow: ellipsis; white-space: nowrap;">Torrent stream</div>
+      <button id="stream-stats-stop" class="focusable" style="margin-left: 10px;">Stop</button>
+    </div>
+    <div style="height: 4px; background: rgba(255, 255, 255, 0.2); border-radius: 2px; overflow: hidden;">
+      <div id="stream-stats-progress" style="height: 100%; width: 0%; background: var(--accent); transition: width 0.3s ease;"></div>
+    </div>
+    <div id="stream-stats-detail" style="font-size: 12px; color: var(--text-secondary); margin-top: 8px;">Waiting for statistics...</div>
+  `;
+  panel.style.cssText = `
+    position: fixed;
+    bottom: 20px;
+    right: 20px;
+    width: 320px;
+    background: var(--bg-secondary);
+    padding: 15px;
+    border-radius: 12px;
+    z-index: 1500;
+    color: var(--text-primary);
+    box-shadow: 0 8px 32px rgba(0, 0, 0, 0.3);
+    border: 1px solid var(--border-color);
+  `;
+  document.body.appendChild(panel);
+
+  document.getElementById('stream-stats-stop').addEventListener('click', async () => {
+    hideStreamStats();
+    appState.activeInfoHash = null;
+    try {
+      await safeInvoke('stop_video_stream');
+    } catch (error) {
+      console.error('Failed to stop torrent stream:', error);
+    }
+  });
+
+  const update = (stats) => {
+    if (!stats || stats.info_hash !== infoHash) return;
+    document.getElementById('stream-stats-title').textContent = stats.file_name;
+    document.getElementById('stream-stats-progress').style.width = `${Math.floor(stats.progress * 100)}%`;
+    document.getElementById('stream-stats-detail').textContent = describeStreamStats(stats);
+  };
+
+  const { listen } = window.__TAURI__.event;
+  const unlisten = await listen('stream-stats', (event) => update(event.payload));
+  // The panel may have been closed while the listener was registered
+  if (document.getElementById('stream-stats-panel') !== panel) {
+    unlisten();
+    return;
+  }
+  unlistenStreamStats = unlisten;
+
+  try {
+    update(await safeInvoke('get_stream_stats'));
+  } catch (error) {
+    console.error('Failed to get stream statistics:', error);
+  }
+}
+
+function hideStreamStats() {
+  if (unlistenStreamStats) {
+    unlistenStreamStats();
+    unlistenStreamStats = null;
+  }
+  const panel = document.getElementById('stream-stats-panel');
+  if (panel) panel.remove();
+}
+
+// Lists a torrent's files once its metadata is known; picking one plays it
+async function showFilePicker(stream) {
+  showStatus('Loading torrent file list...');
+  let list;
+  try {
+    list = await safeInvoke('list_torrent_files', { stream, episode: appState.currentEpisode });
+  } catch (error) {
+    console.error('Failed to list torrent files:', error);
+    showError('Failed to load the file list: ' + (error.message || error));
+    return;
+  }
+  hideStatus();
+
+  const items = list.files
+    .filter(file => file.is_video)
+    .map(file => {
+      const episode = file.episode != null
+        ? ` · ${file.season != null ? `S${file.season}` : ''}E${file.episode}`
+        : '';
+      const item = document.createElement('div');
+      item.className = 'stream-item focusable';
+      item.tabIndex = 0;
+      if (file.index === list.selected) item.classList.add('focused');
+      item.innerHTML = `
+        <div class="stream-title"></div>
+        <div class="stream-quality">${formatBytes(file.size)}${episode}${file.index === list.selected ? ' · selected' : ''}</div>
+      `;
+      item.querySelector('.stream-title').textContent = file.path;
+      item.addEventListener('click', () => {
+        closeStreamModal();
+        tryExternalPlayer(stream, file.index);
+      });
+      return item;
+    });
+
+  if (items.length === 0) {
+    showError('This torrent contains no video files');
+    return;
+  }
+
+  elements.modalMovieTitle.textContent = list.name || 'Choose a file';
+  elements.streamsList.innerHTML = '';
+  items.forEach(item => elements.streamsList.appendChild(item));
+  (elements.streamsList.querySelector('.focused') || items[0]).focus();
+}
+
+async function loadDownloads() {
+  try {
+    appState.downloads = await safeInvoke('list_downloads');
+  } catch (error) {
+    console.error('Failed to load downloads:', error);
+  }
+
+  const { listen } = window.__TAURI__.event;
+  await listen('download-progress', (event) => {
+    const download = event.payload;
+    const index = appState.downloads.findIndex(d => d.id === download.id);
+    const previous = index >= 0 ? appState.downloads[index] : null;
+    if (index >= 0) {
+      appState.downloads[index] = download;
+    } else {
+      appState.downloads.push(download);
+    }
+
+    if (previous && previous.status !== download.status) {
+      if (download.status === 'completed') {
+        showStatus(`Downloaded ${download.title}`, 4000);
+      } else if (download.status === 'failed') {
+        showError(`Download of ${download.title} failed: ${download.error}`);
+      }
+    }
+  });
+}
+
+// The library entry for a stream: same torrent (and file, when the addon names one) or same URL
+// The download of the same file as a stream: for torrents the same file index, or
+// the same episode of a pack; matches download_manager.rs is_download_of
+function findDownload(stream) {
+  const episode = appState.currentEpisode;
+  return appState.downloads.find(d => {
+    if (stream.info_hash) {
+      if (d.stream.info_hash !== stream.info_hash.toLowerCase()) return false;
+      if (stream.file_idx != null) {
+        return d.file_index === stream.file_idx || d.stream.file_idx === stream.file_idx;
+      }
+      if (episode) {
+        return !!d.episode && d.episode.season === episode.season && d.episode.episode === episode.episode;
+      }
+      return d.stream.file_idx == null && !d.episode;
+    }
+    return !d.stream.info_hash && d.stream.url === stream.url;
+  });
+}
+
+async function downloadStream(stream) {
+  const existing = findDownload(stream);
+  try {
+    if (existing && existing.status === 'downloading') {
+      await safeInvoke('pause_download', { id: existing.id });
+      showStatus(`Paused download of ${existing.title}`, 3000);
+      return;
+    }
+    if (existing && existing.status === 'completed') {
+      showStatus(`${existing.title} is already downloaded`, 3000);
+      return;
+    }
+    const title = appState.currentContent?.name || stream.title;
+    const download = await safeInvoke('queue_download', { stream, title, episode: appState.currentEpisode });
+    showStatus(`Downloading ${download.title}`, 3000);
+  } catch (error) {
+    console.error('Failed to download stream:', error);
+    showError('Failed to download: ' + (error.message || error));
+  }
+}
+
+function describeDownload(download) {
+  switch (download.status) {
+    case 'completed': return 'Downloaded';
+    case 'paused': return 'Resume download';
+    case 'failed': return 'Retry download';
+    case 'verifying': return 'Verifying...';
+    default: {
+      const percent = download.total_bytes ? Math.floor(download.downloaded_bytes / download.total_bytes * 100) : 0;
+      return `Pause download (${percent}%)`;
+    }
+  }
+}
+
+function describeStreamStats(stats) {
+  const parts = [
+    `${Math.floor(stats.progress * 100)}% of ${formatBytes(stats.file_size)}`,
+    `${formatBytes(stats.download_speed)}/s`,
+    `${stats.peers} peers`,
+    `${stats.pieces_verified}/${stats.pieces_total} pieces`,
+  ];
+  if (stats.finished) {
+    parts.push('download complete');
+  } else {
+    if (stats.buffered_ahead_secs != null) {
+      parts.push(`${formatDuration(stats.buffered_ahead_secs)} buffered`);
+    } else {
+      parts.push(`${formatBytes(stats.buffered_ahead_bytes)} buffered`);
+    }
+    if (stats.eta_secs != null) {
+      parts.push(`${formatDuration(stats.eta_secs)} left`);
+    }
+  }
+  return parts.join(' • ');
+}
+
+function formatBytes(bytes) {
+  const units = ['B', 'KB', 'MB', 'GB', 'TB'];
+  let value = bytes;
+  let unit = 0;
+  while (value >= 1024 && unit < units.length - 1) {
+    value /= 1024;
+    unit++;
+  }
+  return `${value.toFixed(unit === 0 ? 0 : 1)} ${units[unit]}`;
+}
+
+function formatDuration(secs) {
+  const total = Math.round(secs);
+  const hours = Math.floor(total / 3600);
+  const minutes = Math.floor((total % 3600) / 60);
+  const seconds = total % 60;
+  if (hours > 0) return `${hours}h ${minutes}m`;
+  if (minutes > 0) return `${minutes}m ${seconds}s`;
+  return `${seconds}s`;
+}
+
+function describeStartupFailure(reason, message) {
+  switch (reason) {
+    case 'metadata_timeout':
+    case 'no_peers':
+      return 'No peers are sharing this torrent right now. Try another stream.';
+    case 'buffer_stalled':
+      return 'The torrent stopped downloading before playback could start. Try another stream.';
+    case 'no_files':
+      return 'This torrent has no playable file.';
+    default:
+      return 'Failed to start torrent stream: ' + message;
   }
 }
 
@@ -2347,8 +2832,8 @@ function closeVideoPlayer() {
   // Hide video modal
   elements.videoPlayerModal.classList.add('hidden');
 
-  // Clear stored stream URL
-  window.currentStreamUrl = null;
+  // Clear stored stream
+  window.currentStream = null;
 
   // Return focus to main content
   if (appState.focusedElement) {
@@ -80,11 +80,6 @@ fi
 export PATH=~/.npm-global/bin:$PATH
 print_success "npm configured"
 
-# Install Peerflix
-print_info "Installing Peerflix..."
-npm install -g peerflix
-print_success "Peerflix installed"
-
 # Install MPV
 print_info "Installing MPV video player..."
 if ! command -v mpv &> /dev/null; then